@@ -0,0 +1,35 @@
+-- Metadata for every stored image, keyed by the server generated filename
+CREATE TABLE uploads (
+  id SERIAL PRIMARY KEY,
+  storage_key VARCHAR(255) NOT NULL UNIQUE,
+  original_name VARCHAR(255) NOT NULL,
+  mime VARCHAR(100) NOT NULL,
+  bytes BIGINT NOT NULL,
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Files stored before this table existed get a row too, so cleanup and purge
+-- can find them. Their size and original name were never recorded; the type
+-- is guessed from the name. A key shared by several posts is the same file and
+-- needs one row.
+INSERT INTO uploads (storage_key, original_name, mime, bytes, created_at)
+SELECT DISTINCT ON (key)
+       key,
+       key,
+       CASE
+           WHEN lower(key) LIKE '%.png' THEN 'image/png'
+           WHEN lower(key) LIKE '%.webp' THEN 'image/webp'
+           ELSE 'image/jpeg'
+       END,
+       0,
+       created_at
+FROM (
+  SELECT img.key, p.created_at
+  FROM posts p
+  CROSS JOIN LATERAL unnest(p.imgs) AS img(key)
+  UNION ALL
+  SELECT u.profile, u.created_at
+  FROM users u
+) stored
+WHERE key IS NOT NULL AND key <> ''
+ORDER BY key, created_at;
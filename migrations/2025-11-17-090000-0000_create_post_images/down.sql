@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE posts ADD COLUMN imgs TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE posts p
+SET imgs = sub.keys
+FROM (
+  SELECT post_id, array_agg(storage_key ORDER BY position) AS keys
+  FROM post_images
+  GROUP BY post_id
+) sub
+WHERE sub.post_id = p.id;
+
+ALTER TABLE posts ALTER COLUMN imgs DROP DEFAULT;
+
+DROP TABLE post_images;
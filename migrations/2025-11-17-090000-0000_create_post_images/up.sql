@@ -0,0 +1,58 @@
+CREATE TABLE post_images (
+  id SERIAL PRIMARY KEY,
+  post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+  storage_key VARCHAR(255) NOT NULL UNIQUE,
+  position INTEGER NOT NULL,
+  alt_text VARCHAR(500),
+  width INTEGER,
+  height INTEGER,
+  mime VARCHAR(100) NOT NULL,
+  bytes BIGINT NOT NULL,
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX post_images_post_id_position_idx ON post_images (post_id, position);
+
+-- A key may belong to one post only. Names repeated across or within posts
+-- would have to be re-keyed on disk as well, so stop and let them be fixed by
+-- hand rather than dropping rows.
+DO $$
+DECLARE
+  dupes TEXT;
+BEGIN
+  SELECT string_agg(format('%s (posts %s)', key, post_ids), ', ')
+  INTO dupes
+  FROM (
+    SELECT img.key, string_agg(p.id::TEXT, ',' ORDER BY p.id) AS post_ids
+    FROM posts p
+    CROSS JOIN LATERAL unnest(p.imgs) AS img(key)
+    WHERE img.key IS NOT NULL
+    GROUP BY img.key
+    HAVING count(*) > 1
+  ) d;
+
+  IF dupes IS NOT NULL THEN
+    RAISE EXCEPTION 'posts.imgs references the same image more than once: %', dupes;
+  END IF;
+END
+$$;
+
+-- Move the existing array entries over, keeping their order. Size and type
+-- come from the upload metadata when we have it, otherwise from the name.
+INSERT INTO post_images (post_id, storage_key, position, mime, bytes, created_at)
+SELECT p.id,
+       img.key,
+       img.ord - 1,
+       COALESCE(u.mime, CASE
+           WHEN lower(img.key) LIKE '%.png' THEN 'image/png'
+           WHEN lower(img.key) LIKE '%.webp' THEN 'image/webp'
+           ELSE 'image/jpeg'
+       END),
+       COALESCE(u.bytes, 0),
+       p.created_at
+FROM posts p
+CROSS JOIN LATERAL unnest(p.imgs) WITH ORDINALITY AS img(key, ord)
+LEFT JOIN uploads u ON u.storage_key = img.key
+WHERE img.key IS NOT NULL;
+
+ALTER TABLE posts DROP COLUMN imgs;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX posts_created_at_id_idx;
+DROP TABLE follows;
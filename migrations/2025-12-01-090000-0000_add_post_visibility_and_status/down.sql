@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX posts_userid_status_idx;
+ALTER TABLE posts DROP COLUMN status, DROP COLUMN visibility;
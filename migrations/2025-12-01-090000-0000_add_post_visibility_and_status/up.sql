@@ -0,0 +1,7 @@
+ALTER TABLE posts
+  ADD COLUMN visibility VARCHAR(20) NOT NULL DEFAULT 'public'
+    CHECK (visibility IN ('public', 'followers', 'private', 'unlisted')),
+  ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'published'
+    CHECK (status IN ('draft', 'published', 'archived'));
+
+CREATE INDEX posts_userid_status_idx ON posts (userid, status);
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX posts_scheduled_publish_at_idx;
+DROP INDEX posts_published_at_id_idx;
+CREATE INDEX posts_created_at_id_idx ON posts (created_at DESC, id DESC);
+
+UPDATE posts SET status = 'draft' WHERE status = 'scheduled';
+ALTER TABLE posts
+  DROP COLUMN published_at,
+  DROP COLUMN publish_at,
+  DROP CONSTRAINT posts_status_check;
+ALTER TABLE posts
+  ADD CONSTRAINT posts_status_check CHECK (status IN ('draft', 'published', 'archived'));
@@ -0,0 +1,16 @@
+ALTER TABLE posts DROP CONSTRAINT posts_status_check;
+ALTER TABLE posts
+  ADD CONSTRAINT posts_status_check
+    CHECK (status IN ('draft', 'scheduled', 'published', 'archived')),
+  ADD COLUMN publish_at TIMESTAMP WITHOUT TIME ZONE,
+  ADD COLUMN published_at TIMESTAMP WITHOUT TIME ZONE;
+
+-- Listings are ordered by publication time so queued posts surface when they go live.
+-- created_at is in the database's local time; published_at is UTC like the app writes it.
+UPDATE posts
+SET published_at = created_at AT TIME ZONE current_setting('TimeZone') AT TIME ZONE 'UTC'
+WHERE status = 'published';
+
+DROP INDEX posts_created_at_id_idx;
+CREATE INDEX posts_published_at_id_idx ON posts (published_at DESC, id DESC);
+CREATE INDEX posts_scheduled_publish_at_idx ON posts (publish_at) WHERE status = 'scheduled';
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE post_revisions;
+ALTER TABLE posts DROP COLUMN updated_at;
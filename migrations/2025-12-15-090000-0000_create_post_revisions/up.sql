@@ -0,0 +1,30 @@
+ALTER TABLE posts ADD COLUMN updated_at TIMESTAMP WITHOUT TIME ZONE;
+
+-- One row per version of a post; revision 1 is the content as first uploaded.
+-- `images` holds the storage keys in display order at that point.
+CREATE TABLE post_revisions (
+  id SERIAL PRIMARY KEY,
+  post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+  revision INTEGER NOT NULL,
+  editor_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+  name VARCHAR(100) NOT NULL,
+  description TEXT NOT NULL,
+  images TEXT[] NOT NULL DEFAULT '{}',
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (post_id, revision)
+);
+
+-- Existing posts start their history with their current content
+INSERT INTO post_revisions (post_id, revision, editor_id, name, description, images, created_at)
+SELECT p.id,
+       1,
+       p.userId,
+       p.name,
+       p.description,
+       COALESCE(
+           (SELECT array_agg(pi.storage_key ORDER BY pi.position, pi.id)
+            FROM post_images pi
+            WHERE pi.post_id = p.id),
+           '{}'),
+       p.created_at
+FROM posts p;
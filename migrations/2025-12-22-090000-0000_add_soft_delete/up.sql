@@ -0,0 +1,6 @@
+-- Rows with deleted_at set sit in the trash until the purge job removes them
+ALTER TABLE posts ADD COLUMN deleted_at TIMESTAMP WITHOUT TIME ZONE;
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMP WITHOUT TIME ZONE;
+
+CREATE INDEX posts_deleted_at_idx ON posts (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX users_deleted_at_idx ON users (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE mentions;
+DROP TABLE post_tags;
+DROP TABLE tags;
+ALTER TABLE users DROP COLUMN username;
@@ -0,0 +1,43 @@
+-- Handles that @mentions resolve against; stored lowercased
+ALTER TABLE users ADD COLUMN username VARCHAR(30) UNIQUE;
+
+CREATE TABLE tags (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(100) NOT NULL UNIQUE,
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE post_tags (
+  post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+  tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  PRIMARY KEY (post_id, tag_id)
+);
+
+CREATE INDEX post_tags_tag_id_idx ON post_tags (tag_id);
+
+CREATE TABLE mentions (
+  post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+  user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (post_id, user_id)
+);
+
+CREATE INDEX mentions_user_id_idx ON mentions (user_id);
+
+-- Tag the existing posts; nobody has a username yet, so there are no mentions to backfill
+CREATE TEMPORARY TABLE legacy_post_tags AS
+SELECT DISTINCT p.id AS post_id, lower(m[1]) AS name
+FROM posts p
+CROSS JOIN LATERAL regexp_matches(p.description, '(?:^|[^[:alnum:]_])#([[:alnum:]_]{1,100})', 'g') AS m;
+
+INSERT INTO tags (name)
+SELECT DISTINCT name FROM legacy_post_tags
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO post_tags (post_id, tag_id)
+SELECT l.post_id, t.id
+FROM legacy_post_tags l
+JOIN tags t ON t.name = l.name
+ON CONFLICT DO NOTHING;
+
+DROP TABLE legacy_post_tags;
@@ -0,0 +1,23 @@
+CREATE TABLE notifications (
+  id BIGSERIAL PRIMARY KEY,
+  user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  actor_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+  kind VARCHAR(30) NOT NULL,
+  post_id INTEGER REFERENCES posts(id) ON DELETE CASCADE,
+  read_at TIMESTAMP WITHOUT TIME ZONE,
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX notifications_user_id_id_idx ON notifications (user_id, id DESC);
+CREATE INDEX notifications_unread_idx ON notifications (user_id) WHERE read_at IS NULL;
+
+-- Only deviations from the defaults are stored; a missing row means the
+-- defaults for that kind (in-app on, email and push off)
+CREATE TABLE notification_preferences (
+  user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  kind VARCHAR(30) NOT NULL,
+  in_app BOOLEAN NOT NULL DEFAULT TRUE,
+  email BOOLEAN NOT NULL DEFAULT FALSE,
+  push BOOLEAN NOT NULL DEFAULT FALSE,
+  PRIMARY KEY (user_id, kind)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE messages;
+DROP TABLE conversation_members;
+DROP TABLE conversations;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE moderation_actions;
+DROP TABLE reports;
+ALTER TABLE posts DROP COLUMN hidden_at;
+ALTER TABLE users DROP COLUMN suspension_reason;
+ALTER TABLE users DROP COLUMN suspended_until;
+ALTER TABLE users DROP COLUMN role;
@@ -0,0 +1,38 @@
+-- Moderators work the report queue, admins can also hand out roles
+ALTER TABLE users ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'user';
+ALTER TABLE users ADD COLUMN suspended_until TIMESTAMP WITHOUT TIME ZONE;
+ALTER TABLE users ADD COLUMN suspension_reason TEXT;
+
+-- Hidden by a moderator: only the author still sees the post
+ALTER TABLE posts ADD COLUMN hidden_at TIMESTAMP WITHOUT TIME ZONE;
+
+CREATE TABLE reports (
+  id BIGSERIAL PRIMARY KEY,
+  reporter_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+  target_type VARCHAR(20) NOT NULL,
+  target_id BIGINT NOT NULL,
+  reason VARCHAR(30) NOT NULL,
+  details TEXT,
+  status VARCHAR(20) NOT NULL DEFAULT 'open',
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+  resolved_at TIMESTAMP WITHOUT TIME ZONE,
+  resolved_by BIGINT REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX reports_status_id_idx ON reports (status, id);
+CREATE UNIQUE INDEX reports_one_open_per_reporter_idx
+  ON reports (reporter_id, target_type, target_id) WHERE status = 'open';
+
+-- Append-only record of every moderation decision
+CREATE TABLE moderation_actions (
+  id BIGSERIAL PRIMARY KEY,
+  moderator_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+  report_id BIGINT REFERENCES reports(id) ON DELETE SET NULL,
+  action VARCHAR(30) NOT NULL,
+  target_type VARCHAR(20) NOT NULL,
+  target_id BIGINT NOT NULL,
+  note TEXT,
+  created_at TIMESTAMP WITHOUT TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX moderation_actions_target_idx ON moderation_actions (target_type, target_id);
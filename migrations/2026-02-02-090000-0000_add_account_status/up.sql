@@ -0,0 +1,8 @@
+-- Account standing; suspensions end on their own once suspended_until passes
+ALTER TABLE users ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'active'
+  CHECK (status IN ('active', 'suspended', 'banned', 'deactivated'));
+-- Tokens issued before the account last lost its standing are refused
+ALTER TABLE users ADD COLUMN status_changed_at TIMESTAMP WITHOUT TIME ZONE;
+
+UPDATE users SET status = 'suspended', status_changed_at = NOW()
+WHERE suspended_until > NOW();
@@ -0,0 +1,29 @@
+-- Security-sensitive actions. Ids are kept as plain values so entries outlive
+-- the users and posts they mention.
+CREATE TABLE audit_events (
+  id BIGSERIAL PRIMARY KEY,
+  actor_id BIGINT,
+  action VARCHAR(40) NOT NULL,
+  target_type VARCHAR(20),
+  target_id BIGINT,
+  ip VARCHAR(64),
+  user_agent TEXT,
+  before JSONB,
+  after JSONB,
+  created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_events_actor_idx ON audit_events (actor_id, created_at);
+CREATE INDEX audit_events_target_idx ON audit_events (target_type, target_id, created_at);
+CREATE INDEX audit_events_action_idx ON audit_events (action, created_at);
+
+-- Append-only: entries can be added but never changed or removed
+CREATE FUNCTION audit_events_append_only() RETURNS trigger AS $$
+BEGIN
+  RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+BEFORE UPDATE OR DELETE ON audit_events
+FOR EACH ROW EXECUTE FUNCTION audit_events_append_only();
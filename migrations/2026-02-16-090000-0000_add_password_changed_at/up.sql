@@ -0,0 +1,2 @@
+-- Tokens issued before the password was last set are refused
+ALTER TABLE users ADD COLUMN password_changed_at TIMESTAMP WITHOUT TIME ZONE;
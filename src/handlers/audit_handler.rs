@@ -0,0 +1,119 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::NaiveDateTime;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    db::Pool,
+    models::user::{AuditEvent, ROLE_ADMIN},
+    schema::audit_events,
+    utils::auth::require_claims,
+    utils::roles::require_role,
+};
+
+type AuditListing<'a> = audit_events::BoxedQuery<'a, Pg>;
+
+fn bad_filter(name: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "status": false,
+        "message": format!("Invalid {} filter", name)
+    }))
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    value.parse::<NaiveDateTime>().ok().or_else(|| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|t| t.naive_utc())
+    })
+}
+
+/// Apply the query string filters shared by the page and the total.
+#[allow(clippy::result_large_err)]
+fn filtered<'a>(query: &HashMap<String, String>) -> Result<AuditListing<'a>, HttpResponse> {
+    let mut listing = audit_events::table.into_boxed();
+
+    if let Some(actor) = query.get("actor_id") {
+        let actor = actor.parse::<i64>().map_err(|_| bad_filter("actor_id"))?;
+        listing = listing.filter(audit_events::actor_id.eq(actor));
+    }
+    if let Some(action) = query.get("action") {
+        listing = listing.filter(audit_events::action.eq(action.clone()));
+    }
+    if let Some(target_type) = query.get("target_type") {
+        listing = listing.filter(audit_events::target_type.eq(target_type.clone()));
+    }
+    if let Some(target) = query.get("target_id") {
+        let target = target.parse::<i64>().map_err(|_| bad_filter("target_id"))?;
+        listing = listing.filter(audit_events::target_id.eq(target));
+    }
+    if let Some(ip) = query.get("ip") {
+        listing = listing.filter(audit_events::ip.eq(ip.clone()));
+    }
+    if let Some(since) = query.get("since") {
+        let since = parse_time(since).ok_or_else(|| bad_filter("since"))?;
+        listing = listing.filter(audit_events::created_at.ge(since));
+    }
+    if let Some(until) = query.get("until") {
+        let until = parse_time(until).ok_or_else(|| bad_filter("until"))?;
+        listing = listing.filter(audit_events::created_at.lt(until));
+    }
+    Ok(listing)
+}
+
+/// Audit log, newest first. Filters: `actor_id`, `action`, `target_type`,
+/// `target_id`, `ip`, `since` and `until` (ISO 8601).
+pub async fn list_audit_events(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_role(&mut conn, claims.id, &[ROLE_ADMIN]) {
+        return resp;
+    }
+
+    let page = query
+        .get("page")
+        .and_then(|p| p.parse::<i64>().ok())
+        .filter(|p| *p >= 1)
+        .unwrap_or(1);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .filter(|l| (1..=100).contains(l))
+        .unwrap_or(50);
+
+    let (page_query, total_query) = match (filtered(&query), filtered(&query)) {
+        (Ok(page_query), Ok(total_query)) => (page_query, total_query),
+        (Err(resp), _) | (_, Err(resp)) => return resp,
+    };
+
+    let items = page_query
+        .order(audit_events::id.desc())
+        .limit(limit)
+        .offset((page - 1) * limit)
+        .load::<AuditEvent>(&mut conn);
+    let total = total_query.count().get_result::<i64>(&mut conn);
+
+    match items.and_then(|items| total.map(|total| (items, total))) {
+        Ok((items, total)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "events": items,
+            "total_events": total
+        })),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    db::Pool,
+    handlers::follow_handler::page_params,
+    handlers::user_handler::{UsersResponse, user_data_columns},
+    models::user::{NewBlock, NewMute, UserData},
+    schema::{blocks, follows, mutes, users},
+    utils::auth::require_claims,
+    utils::blocks::{blocked_by, muted_by},
+    utils::soft_delete::live_users,
+};
+
+/// 400 for the caller themselves, 404 for unknown or trashed users.
+#[allow(clippy::result_large_err)]
+fn check_target(
+    conn: &mut PgConnection,
+    caller_id: i64,
+    target_id: i64,
+    verb: &str,
+) -> Result<(), HttpResponse> {
+    if target_id == caller_id {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("You cannot {} yourself", verb)
+        })));
+    }
+
+    let found = live_users()
+        .filter(users::id.eq(target_id))
+        .select(users::id)
+        .first::<i64>(conn)
+        .optional();
+    match found {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "User not found"
+        }))),
+        Err(e) => Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": e.to_string()
+        }))),
+    }
+}
+
+/// Block a user. Follows between the two are dropped in both directions.
+pub async fn block_user(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = check_target(&mut conn, claims.id, target_id, "block") {
+        return resp;
+    }
+
+    let blocked = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(blocks::table)
+            .values(&NewBlock {
+                blocker_id: claims.id,
+                blocked_id: target_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        diesel::delete(
+            follows::table.filter(
+                follows::follower_id
+                    .eq(claims.id)
+                    .and(follows::followee_id.eq(target_id))
+                    .or(follows::follower_id
+                        .eq(target_id)
+                        .and(follows::followee_id.eq(claims.id))),
+            ),
+        )
+        .execute(conn)
+    });
+
+    match blocked {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "User blocked successfully"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": "Failed to block user",
+            "error": e.to_string()
+        })),
+    }
+}
+
+pub async fn unblock_user(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let deleted = diesel::delete(
+        blocks::table
+            .filter(blocks::blocker_id.eq(claims.id))
+            .filter(blocks::blocked_id.eq(target_id)),
+    )
+    .execute(&mut conn);
+
+    match deleted {
+        Ok(0) => HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "You have not blocked this user"
+        })),
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "User unblocked successfully"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": "Failed to unblock user",
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// Mute a user: their posts and notifications stop reaching the caller,
+/// nothing changes for them.
+pub async fn mute_user(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = check_target(&mut conn, claims.id, target_id, "mute") {
+        return resp;
+    }
+
+    let inserted = diesel::insert_into(mutes::table)
+        .values(&NewMute {
+            muter_id: claims.id,
+            muted_id: target_id,
+        })
+        .on_conflict_do_nothing()
+        .execute(&mut conn);
+
+    match inserted {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "User muted successfully"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": "Failed to mute user",
+            "error": e.to_string()
+        })),
+    }
+}
+
+pub async fn unmute_user(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let deleted = diesel::delete(
+        mutes::table
+            .filter(mutes::muter_id.eq(claims.id))
+            .filter(mutes::muted_id.eq(target_id)),
+    )
+    .execute(&mut conn);
+
+    match deleted {
+        Ok(0) => HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "You have not muted this user"
+        })),
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "User unmuted successfully"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": "Failed to unmute user",
+            "error": e.to_string()
+        })),
+    }
+}
+
+pub async fn get_blocked_users(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let (limit, offset) = page_params(&query);
+    let mut conn = pool.get().expect("DB connection error");
+
+    let total_users: i64 = live_users()
+        .filter(users::id.eq_any(blocked_by(claims.id)))
+        .count()
+        .get_result(&mut conn)
+        .unwrap_or(0);
+
+    let user_list = live_users()
+        .filter(users::id.eq_any(blocked_by(claims.id)))
+        .select(user_data_columns())
+        .order(users::id)
+        .limit(limit)
+        .offset(offset)
+        .load::<UserData>(&mut conn)
+        .unwrap_or_default();
+
+    HttpResponse::Ok().json(UsersResponse {
+        status: true,
+        total_users,
+        users: user_list,
+    })
+}
+
+pub async fn get_muted_users(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let (limit, offset) = page_params(&query);
+    let mut conn = pool.get().expect("DB connection error");
+
+    let total_users: i64 = live_users()
+        .filter(users::id.eq_any(muted_by(claims.id)))
+        .count()
+        .get_result(&mut conn)
+        .unwrap_or(0);
+
+    let user_list = live_users()
+        .filter(users::id.eq_any(muted_by(claims.id)))
+        .select(user_data_columns())
+        .order(users::id)
+        .limit(limit)
+        .offset(offset)
+        .load::<UserData>(&mut conn)
+        .unwrap_or_default();
+
+    HttpResponse::Ok().json(UsersResponse {
+        status: true,
+        total_users,
+        users: user_list,
+    })
+}
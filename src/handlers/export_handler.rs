@@ -0,0 +1,164 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, http::header, web};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    db::Pool,
+    handlers::post_image_handler::{load_post_images, to_post_data},
+    models::user::{Post, PostRevision, User},
+    schema::{post_revisions, posts, users},
+    utils::auth::require_claims,
+    utils::soft_delete::{live_users, post_files},
+    utils::{file_upload::PROFILE_DIR, img_upload::POST_DIR},
+};
+
+/// Everything the archive holds, gathered from the database up front.
+struct ExportData {
+    profile: serde_json::Value,
+    posts: serde_json::Value,
+    profile_image: String,
+    post_images: Vec<String>,
+}
+
+fn collect_export(conn: &mut PgConnection, user_id: i64) -> QueryResult<Option<ExportData>> {
+    let user = match live_users()
+        .filter(users::id.eq(user_id))
+        .first::<User>(conn)
+        .optional()?
+    {
+        Some(u) => u,
+        None => return Ok(None),
+    };
+
+    // Every post the user owns, whatever its status, including ones in the trash
+    let own_posts = posts::table
+        .filter(posts::userid.eq(user_id))
+        .order(posts::id)
+        .load::<Post>(conn)?;
+    let post_ids: Vec<i32> = own_posts.iter().map(|p| p.id).collect();
+    let mut images = load_post_images(conn, &post_ids)?;
+    let revisions = post_revisions::table
+        .filter(post_revisions::post_id.eq_any(&post_ids))
+        .order((post_revisions::post_id, post_revisions::revision))
+        .load::<PostRevision>(conn)?;
+
+    let posts_json: Vec<serde_json::Value> = own_posts
+        .into_iter()
+        .map(|p| {
+            let deleted_at = p.deleted_at;
+            let history: Vec<&PostRevision> =
+                revisions.iter().filter(|r| r.post_id == p.id).collect();
+            let imgs = images.remove(&p.id).unwrap_or_default();
+            serde_json::json!({
+                "post": to_post_data(p, imgs),
+                "deleted_at": deleted_at,
+                "revisions": history
+            })
+        })
+        .collect();
+
+    Ok(Some(ExportData {
+        profile: serde_json::json!({
+            "id": user.id,
+            "email": user.email,
+            "firstname": user.firstname,
+            "lastname": user.lastname,
+            "ph": user.ph,
+            "profile": user.profile,
+            "created_at": user.created_at,
+            "updated_at": user.updated_at
+        }),
+        posts: serde_json::Value::Array(posts_json),
+        profile_image: user.profile,
+        post_images: post_files(conn, &post_ids)?,
+    }))
+}
+
+/// Write the archive: JSON documents at the top, images under `images/`.
+/// Files that went missing from storage are skipped.
+fn build_zip(data: &ExportData) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let json_opts = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Images are already compressed
+    let image_opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for (name, value) in [("profile.json", &data.profile), ("posts.json", &data.posts)] {
+        zip.start_file(name, json_opts)?;
+        zip.write_all(&serde_json::to_vec_pretty(value).unwrap_or_default())?;
+    }
+
+    let files = std::iter::once((PROFILE_DIR, "profile", &data.profile_image))
+        .chain(data.post_images.iter().map(|key| (POST_DIR, "posts", key)));
+    for (dir, folder, key) in files {
+        match std::fs::read(Path::new(dir).join(key)) {
+            Ok(bytes) => {
+                zip.start_file(format!("images/{}/{}", folder, key), image_opts)?;
+                zip.write_all(&bytes)?;
+            }
+            Err(e) => eprintln!("Skipping {}/{} in export: {}", dir, key, e),
+        }
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Download a ZIP of the caller's profile, posts and uploaded images.
+pub async fn export_user_data(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    if claims.id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "status": false,
+            "message": "You can only export your own data"
+        }));
+    }
+
+    let pool = pool.into_inner();
+    let archive = web::block(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        match collect_export(&mut conn, user_id).map_err(|e| e.to_string())? {
+            Some(data) => build_zip(&data).map(Some).map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    })
+    .await;
+
+    match archive {
+        Ok(Ok(Some(bytes))) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"user-{}-export.zip\"", user_id),
+            ))
+            .body(bytes),
+        Ok(Ok(None)) => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "User not found"
+        })),
+        Ok(Err(e)) => {
+            eprintln!("Export failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to export data",
+                "error": e
+            }))
+        }
+        Err(e) => {
+            eprintln!("Export task failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to export data"
+            }))
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    db::Pool,
+    handlers::user_handler::{UsersResponse, user_data_columns},
+    models::user::{NewFollow, UserData},
+    schema::{follows, users},
+    utils::auth::require_claims,
+    utils::blocks::users_visible_to,
+    utils::notifications::{DomainEvent, publish_all},
+};
+
+pub async fn follow_user(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    if target_id == claims.id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "You cannot follow yourself"
+        }));
+    }
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let target_exists = users_visible_to(claims.id)
+        .filter(users::id.eq(target_id))
+        .select(users::id)
+        .first::<i64>(&mut conn)
+        .optional();
+
+    match target_exists {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": "User not found"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": e.to_string()
+            }));
+        }
+    }
+
+    let inserted = diesel::insert_into(follows::table)
+        .values(&NewFollow {
+            follower_id: claims.id,
+            followee_id: target_id,
+        })
+        .on_conflict_do_nothing()
+        .execute(&mut conn);
+
+    match inserted {
+        Ok(count) => {
+            // Re-following is a no-op and should not ping the user again
+            if count > 0 {
+                publish_all(
+                    &mut conn,
+                    vec![DomainEvent::Followed {
+                        follower_id: claims.id,
+                        followee_id: target_id,
+                    }],
+                );
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "User followed successfully"
+            }))
+        }
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": "Failed to follow user",
+            "error": e.to_string()
+        })),
+    }
+}
+
+pub async fn unfollow_user(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let deleted = diesel::delete(
+        follows::table
+            .filter(follows::follower_id.eq(claims.id))
+            .filter(follows::followee_id.eq(target_id)),
+    )
+    .execute(&mut conn);
+
+    match deleted {
+        Ok(0) => HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "You are not following this user"
+        })),
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "User unfollowed successfully"
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": "Failed to unfollow user",
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// `(limit, offset)` from `page` and `limit`, 10 per page by default.
+pub fn page_params(query: &HashMap<String, String>) -> (i64, i64) {
+    let page = query
+        .get("page")
+        .and_then(|p| p.parse::<i64>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .unwrap_or(10)
+        .clamp(1, 100);
+    (limit, (page - 1) * limit)
+}
+
+pub async fn get_followers(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let (limit, offset) = page_params(&query);
+    let mut conn = pool.get().expect("DB connection error");
+
+    let follower_ids = follows::table
+        .filter(follows::followee_id.eq(user_id))
+        .select(follows::follower_id);
+
+    let total_users: i64 = users_visible_to(claims.id)
+        .filter(users::id.eq_any(follower_ids))
+        .count()
+        .get_result(&mut conn)
+        .unwrap_or(0);
+
+    let user_list = users_visible_to(claims.id)
+        .filter(users::id.eq_any(follower_ids))
+        .select(user_data_columns())
+        .order(users::id)
+        .limit(limit)
+        .offset(offset)
+        .load::<UserData>(&mut conn)
+        .unwrap_or_default();
+
+    HttpResponse::Ok().json(UsersResponse {
+        status: true,
+        total_users,
+        users: user_list,
+    })
+}
+
+pub async fn get_following(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let (limit, offset) = page_params(&query);
+    let mut conn = pool.get().expect("DB connection error");
+
+    let followee_ids = follows::table
+        .filter(follows::follower_id.eq(user_id))
+        .select(follows::followee_id);
+
+    let total_users: i64 = users_visible_to(claims.id)
+        .filter(users::id.eq_any(followee_ids))
+        .count()
+        .get_result(&mut conn)
+        .unwrap_or(0);
+
+    let user_list = users_visible_to(claims.id)
+        .filter(users::id.eq_any(followee_ids))
+        .select(user_data_columns())
+        .order(users::id)
+        .limit(limit)
+        .offset(offset)
+        .load::<UserData>(&mut conn)
+        .unwrap_or_default();
+
+    HttpResponse::Ok().json(UsersResponse {
+        status: true,
+        total_users,
+        users: user_list,
+    })
+}
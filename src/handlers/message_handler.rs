@@ -0,0 +1,642 @@
+use actix_files::NamedFile;
+use actix_web::http::header::{CACHE_CONTROL, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use diesel::dsl::count_star;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{
+    db::Pool,
+    handlers::user_handler::user_data_columns,
+    models::user::{
+        Conversation, ConversationSummary, MarkConversationReadRequest, Message, MessageForm,
+        NewConversationMember, NewMessage, StartConversationRequest, UserData,
+    },
+    schema::{conversation_members, conversations, messages, uploads, users},
+    utils::auth::require_claims,
+    utils::blocks::{blocked_between, blocked_by},
+    utils::soft_delete::live_users,
+    utils::stream_hub::{Audience, STREAM_MESSAGE, StreamEvent, emit},
+    utils::{
+        img_upload::{MESSAGE_DIR, save_images_in},
+        multipart_form::MultipartForm,
+        upload::remove_stored_files,
+        validation::Validator,
+    },
+};
+
+/// Upper bound on people in one conversation, the caller included.
+const MAX_CONVERSATION_MEMBERS: usize = 20;
+
+/// Everyone in a conversation, live or not.
+fn member_ids(conn: &mut PgConnection, conversation_id: i32) -> QueryResult<Vec<i64>> {
+    conversation_members::table
+        .filter(conversation_members::conversation_id.eq(conversation_id))
+        .select(conversation_members::user_id)
+        .load::<i64>(conn)
+}
+
+/// Succeeds when `user_id` belongs to the conversation. Outsiders get the
+/// same 404 as for a conversation that doesn't exist.
+#[allow(clippy::result_large_err)]
+fn require_member(
+    conn: &mut PgConnection,
+    conversation_id: i32,
+    user_id: i64,
+) -> Result<(), HttpResponse> {
+    let found = conversation_members::table
+        .filter(conversation_members::conversation_id.eq(conversation_id))
+        .filter(conversation_members::user_id.eq(user_id))
+        .count()
+        .get_result::<i64>(conn);
+
+    match found {
+        Ok(n) if n > 0 => Ok(()),
+        Ok(_) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "Conversation not found"
+        }))),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            })))
+        }
+    }
+}
+
+/// Summaries of `ids` as seen by `viewer_id`, in the order given.
+fn conversation_summaries(
+    conn: &mut PgConnection,
+    viewer_id: i64,
+    ids: &[i32],
+) -> QueryResult<Vec<ConversationSummary>> {
+    let found = conversations::table
+        .filter(conversations::id.eq_any(ids))
+        .load::<Conversation>(conn)?;
+
+    let member_rows = conversation_members::table
+        .inner_join(users::table)
+        .filter(conversation_members::conversation_id.eq_any(ids))
+        .filter(users::deleted_at.is_null())
+        .order((conversation_members::conversation_id, users::id))
+        .select((conversation_members::conversation_id, user_data_columns()))
+        .load::<(i32, UserData)>(conn)?;
+    let mut members: HashMap<i32, Vec<UserData>> = HashMap::new();
+    for (conversation_id, user) in member_rows {
+        members.entry(conversation_id).or_default().push(user);
+    }
+
+    let mut last_messages: HashMap<i32, Message> = messages::table
+        .filter(messages::conversation_id.eq_any(ids))
+        .distinct_on(messages::conversation_id)
+        .order((messages::conversation_id, messages::id.desc()))
+        .load::<Message>(conn)?
+        .into_iter()
+        .map(|m| (m.conversation_id, m))
+        .collect();
+
+    let unread: HashMap<i32, i64> = messages::table
+        .inner_join(
+            conversation_members::table.on(conversation_members::conversation_id
+                .eq(messages::conversation_id)
+                .and(conversation_members::user_id.eq(viewer_id))),
+        )
+        .filter(messages::conversation_id.eq_any(ids))
+        .filter(messages::sender_id.ne(viewer_id))
+        .filter(
+            conversation_members::last_read_message_id
+                .is_null()
+                .or(messages::id
+                    .nullable()
+                    .gt(conversation_members::last_read_message_id)),
+        )
+        .group_by(messages::conversation_id)
+        .select((messages::conversation_id, count_star()))
+        .load::<(i32, i64)>(conn)?
+        .into_iter()
+        .collect();
+
+    let mut by_id: HashMap<i32, Conversation> = found.into_iter().map(|c| (c.id, c)).collect();
+    Ok(ids
+        .iter()
+        .filter_map(|id| by_id.remove(id))
+        .map(|c| ConversationSummary {
+            id: c.id,
+            members: members.remove(&c.id).unwrap_or_default(),
+            last_message: last_messages.remove(&c.id),
+            unread: unread.get(&c.id).copied().unwrap_or(0),
+            last_message_at: c.last_message_at,
+        })
+        .collect())
+}
+
+/// The one-to-one conversation between two users, if they already have one.
+fn direct_conversation(conn: &mut PgConnection, a: i64, b: i64) -> QueryResult<Option<i32>> {
+    let of_a = conversation_members::table
+        .filter(conversation_members::user_id.eq(a))
+        .select(conversation_members::conversation_id)
+        .load::<i32>(conn)?;
+    let of_b = conversation_members::table
+        .filter(conversation_members::user_id.eq(b))
+        .filter(conversation_members::conversation_id.eq_any(&of_a))
+        .select(conversation_members::conversation_id)
+        .load::<i32>(conn)?;
+
+    conversation_members::table
+        .filter(conversation_members::conversation_id.eq_any(&of_b))
+        .group_by(conversation_members::conversation_id)
+        .having(count_star().eq(2))
+        .select(conversation_members::conversation_id)
+        .first::<i32>(conn)
+        .optional()
+}
+
+/// Start a conversation with `member_ids`. Asking for a one-to-one
+/// conversation that already exists returns that one instead.
+pub async fn start_conversation(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    body: web::Json<StartConversationRequest>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    let mut others: Vec<i64> = Vec::new();
+    for id in &body.member_ids {
+        if *id != claims.id && !others.contains(id) {
+            others.push(*id);
+        }
+    }
+    if others.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "Add at least one other member"
+        }));
+    }
+    if others.len() + 1 > MAX_CONVERSATION_MEMBERS {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("A conversation can have at most {} members", MAX_CONVERSATION_MEMBERS)
+        }));
+    }
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let existing_users = live_users()
+        .filter(users::id.eq_any(&others))
+        .select(users::id)
+        .load::<i64>(&mut conn);
+    match existing_users {
+        Ok(found) if found.len() == others.len() => {}
+        Ok(found) => {
+            let missing: Vec<&i64> = others.iter().filter(|id| !found.contains(id)).collect();
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": "User not found",
+                "missing": missing
+            }));
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    }
+    match blocked_between(&mut conn, claims.id, &others) {
+        Ok(false) => {}
+        Ok(true) => {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "status": false,
+                "message": "You can't message this user"
+            }));
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    }
+
+    let started = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        if let [other] = others[..]
+            && let Some(id) = direct_conversation(conn, claims.id, other)?
+        {
+            return Ok((id, false));
+        }
+
+        let id = diesel::insert_into(conversations::table)
+            .values(conversations::created_by.eq(Some(claims.id)))
+            .returning(conversations::id)
+            .get_result::<i32>(conn)?;
+        let rows: Vec<NewConversationMember> = std::iter::once(claims.id)
+            .chain(others.iter().copied())
+            .map(|user_id| NewConversationMember {
+                conversation_id: id,
+                user_id,
+            })
+            .collect();
+        diesel::insert_into(conversation_members::table)
+            .values(&rows)
+            .execute(conn)?;
+        Ok((id, true))
+    });
+
+    let summary = started.and_then(|(id, created)| {
+        conversation_summaries(&mut conn, claims.id, &[id]).map(|mut s| (s.pop(), created))
+    });
+
+    match summary {
+        Ok((conversation, true)) => HttpResponse::Created().json(serde_json::json!({
+            "status": true,
+            "message": "Conversation started",
+            "conversation": conversation
+        })),
+        Ok((conversation, false)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Conversation already exists",
+            "conversation": conversation
+        })),
+        Err(e) => {
+            eprintln!("Database insert failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to start conversation",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// The caller's conversations, most recently active first.
+pub async fn list_conversations(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let page = query
+        .get("page")
+        .and_then(|p| p.parse::<i64>().ok())
+        .filter(|p| *p >= 1)
+        .unwrap_or(1);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .filter(|l| (1..=100).contains(l))
+        .unwrap_or(20);
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let result = conversations::table
+        .inner_join(conversation_members::table)
+        .filter(conversation_members::user_id.eq(claims.id))
+        .order((
+            conversations::last_message_at.desc().nulls_last(),
+            conversations::id.desc(),
+        ))
+        .select(conversations::id)
+        .limit(limit)
+        .offset((page - 1) * limit)
+        .load::<i32>(&mut conn)
+        .and_then(|ids| conversation_summaries(&mut conn, claims.id, &ids));
+
+    match result {
+        Ok(items) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "conversations": items
+        })),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Newest first. `before` takes the last id seen for the next page.
+pub async fn list_messages(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let conversation_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .filter(|l| (1..=100).contains(l))
+        .unwrap_or(30);
+
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_member(&mut conn, conversation_id, claims.id) {
+        return resp;
+    }
+
+    // Earlier messages from someone the caller blocked stay out of sight
+    let mut listing = messages::table
+        .filter(messages::conversation_id.eq(conversation_id))
+        .filter(diesel::dsl::not(
+            messages::sender_id.eq_any(blocked_by(claims.id)),
+        ))
+        .into_boxed();
+    if let Some(before) = query.get("before").and_then(|b| b.parse::<i64>().ok()) {
+        listing = listing.filter(messages::id.lt(before));
+    }
+
+    match listing
+        .order(messages::id.desc())
+        .limit(limit)
+        .load::<Message>(&mut conn)
+    {
+        Ok(items) => {
+            let next_before = if items.len() as i64 == limit {
+                items.last().map(|m| m.id)
+            } else {
+                None
+            };
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "messages": items,
+                "next_before": next_before
+            }))
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Multipart with a `body` text field and up to four `images`.
+pub async fn send_message(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    payload: MultipartForm<MessageForm>,
+) -> impl Responder {
+    let conversation_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_member(&mut conn, conversation_id, claims.id) {
+        return resp;
+    }
+    // A block with anyone in the conversation stops new messages to it
+    let blocked = member_ids(&mut conn, conversation_id)
+        .and_then(|ids| blocked_between(&mut conn, claims.id, &ids));
+    match blocked {
+        Ok(false) => {}
+        Ok(true) => {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "status": false,
+                "message": "You can't message this conversation"
+            }));
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    }
+
+    let MultipartForm {
+        fields: MessageForm { body: body_field },
+        mut files,
+    } = payload;
+    let files = files.take("images");
+
+    if let Err(e) = Validator::validate_message(&body_field, files.len()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
+    }
+
+    let saved_images = match save_images_in(MESSAGE_DIR, files) {
+        Ok(v) => v,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({"status": false, "message": e}));
+        }
+    };
+    let stored_keys: Vec<String> = saved_images
+        .iter()
+        .map(|img| img.storage_key.clone())
+        .collect();
+
+    let sent = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(uploads::table)
+            .values(&saved_images)
+            .execute(conn)?;
+        let message = diesel::insert_into(messages::table)
+            .values(&NewMessage {
+                conversation_id,
+                sender_id: claims.id,
+                body: body_field.trim().to_string(),
+                images: stored_keys.iter().cloned().map(Some).collect(),
+            })
+            .get_result::<Message>(conn)?;
+        diesel::update(conversations::table.filter(conversations::id.eq(conversation_id)))
+            .set(conversations::last_message_at.eq(message.created_at))
+            .execute(conn)?;
+        // Your own message is never unread
+        diesel::update(
+            conversation_members::table
+                .filter(conversation_members::conversation_id.eq(conversation_id))
+                .filter(conversation_members::user_id.eq(claims.id)),
+        )
+        .set(conversation_members::last_read_message_id.eq(Some(message.id)))
+        .execute(conn)?;
+        Ok(message)
+    });
+
+    match sent {
+        Ok(message) => {
+            match member_ids(&mut conn, conversation_id) {
+                Ok(members) => emit(
+                    &mut conn,
+                    StreamEvent {
+                        event: STREAM_MESSAGE.to_string(),
+                        id: message.id,
+                        audience: Audience::Users(members),
+                        data: serde_json::to_value(&message).unwrap_or_default(),
+                    },
+                ),
+                Err(e) => eprintln!("Stream event for message {} failed: {}", message.id, e),
+            }
+
+            HttpResponse::Created().json(serde_json::json!({
+                "status": true,
+                "message": "Message sent",
+                "data": message
+            }))
+        }
+        Err(e) => {
+            eprintln!("Database insert failed: {}", e);
+            remove_stored_files(MESSAGE_DIR, &stored_keys);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to send message",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Move the caller's read marker forward to `message_id`, or to the latest message.
+pub async fn mark_conversation_read(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    body: web::Json<MarkConversationReadRequest>,
+) -> impl Responder {
+    let conversation_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_member(&mut conn, conversation_id, claims.id) {
+        return resp;
+    }
+
+    let mut target = messages::table
+        .filter(messages::conversation_id.eq(conversation_id))
+        .into_boxed();
+    if let Some(id) = body.message_id {
+        target = target.filter(messages::id.eq(id));
+    }
+    let target = target
+        .order(messages::id.desc())
+        .select(messages::id)
+        .first::<i64>(&mut conn)
+        .optional();
+
+    let message_id = match target {
+        Ok(Some(id)) => id,
+        Ok(None) if body.message_id.is_some() => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": "Message not found"
+            }));
+        }
+        Ok(None) => {
+            return HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Nothing to mark as read"
+            }));
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    };
+
+    // Never move the marker backwards
+    let updated = diesel::update(
+        conversation_members::table
+            .filter(conversation_members::conversation_id.eq(conversation_id))
+            .filter(conversation_members::user_id.eq(claims.id))
+            .filter(
+                conversation_members::last_read_message_id
+                    .is_null()
+                    .or(conversation_members::last_read_message_id.lt(message_id)),
+            ),
+    )
+    .set(conversation_members::last_read_message_id.eq(Some(message_id)))
+    .execute(&mut conn);
+
+    match updated {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Conversation marked as read",
+            "last_read_message_id": message_id
+        })),
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to mark conversation as read",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Serve an image attached to a message in the conversation, to members only.
+pub async fn get_message_image(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(i32, String)>,
+) -> impl Responder {
+    let (conversation_id, key) = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_member(&mut conn, conversation_id, claims.id) {
+        return resp;
+    }
+
+    let attached = messages::table
+        .filter(messages::conversation_id.eq(conversation_id))
+        .filter(messages::images.contains(vec![Some(key.clone())]))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .unwrap_or(0);
+    let file = if attached > 0 {
+        NamedFile::open(Path::new(MESSAGE_DIR).join(&key)).ok()
+    } else {
+        None
+    };
+
+    match file {
+        Some(file) => {
+            let mut response = file.into_response(&req);
+            response.headers_mut().insert(
+                CACHE_CONTROL,
+                HeaderValue::from_static("private, max-age=86400"),
+            );
+            response
+        }
+        None => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "Image not found"
+        })),
+    }
+}
@@ -1,2 +1,15 @@
 pub mod user_handler;
-pub mod post_handler;
\ No newline at end of file
+pub mod post_handler;
+pub mod post_image_handler;
+pub mod follow_handler;
+pub mod schedule_handler;
+pub mod revision_handler;
+pub mod trash_handler;
+pub mod export_handler;
+pub mod tag_handler;
+pub mod notification_handler;
+pub mod stream_handler;
+pub mod message_handler;
+pub mod moderation_handler;
+pub mod block_handler;
+pub mod audit_handler;
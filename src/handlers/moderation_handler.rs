@@ -0,0 +1,749 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use std::collections::HashMap;
+
+use crate::{
+    db::Pool,
+    models::user::{
+        AUDIT_MODERATION_DECISION, AUDIT_PASSWORD_CHANGED, AUDIT_ROLE_CHANGED,
+        AUDIT_STATUS_CHANGED, AUDIT_TARGET_REPORT, AUDIT_TARGET_USER, MODERATION_DECISIONS,
+        MODERATION_DISMISS, MODERATION_HIDE_POST, MODERATION_RESET_PASSWORD, MODERATION_SET_ROLE,
+        MODERATION_SET_STATUS, MODERATION_SUSPEND_USER, ModerationAction, ModerationDecision,
+        NewModerationAction, NewReport, PasswordReset, REPORT_REASONS, REPORT_STATUS_DISMISSED,
+        REPORT_STATUS_OPEN, REPORT_STATUS_RESOLVED, REPORT_TARGET_POST, REPORT_TARGET_USER,
+        ROLE_ADMIN, ROLE_MODERATOR, ROLE_USER, ROLES, Report, RoleUpdate, StatusUpdate,
+        USER_STATUS_ACTIVE, USER_STATUS_BANNED, USER_STATUS_SUSPENDED, USER_STATUSES,
+    },
+    schema::{moderation_actions, posts, reports, users},
+    utils::audit,
+    utils::auth::require_claims,
+    utils::password::{PasswordError, set_password},
+    utils::post_access::{post_listing, viewable_by},
+    utils::roles::require_role,
+    utils::soft_delete::live_users,
+    utils::stream_hub::{STREAM_POST_DELETED, emit_post},
+};
+
+const STAFF: [&str; 2] = [ROLE_MODERATOR, ROLE_ADMIN];
+
+fn db_error(e: DieselError) -> HttpResponse {
+    eprintln!("Database query failed: {}", e);
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "status": false,
+        "message": "Database error",
+        "error": e.to_string()
+    }))
+}
+
+/// Author of a live post, if it exists.
+fn post_author(conn: &mut PgConnection, post_id: i64) -> QueryResult<Option<i64>> {
+    let Ok(post_id) = i32::try_from(post_id) else {
+        return Ok(None);
+    };
+    posts::table
+        .filter(posts::id.eq(post_id))
+        .filter(posts::deleted_at.is_null())
+        .select(posts::userid)
+        .first::<i64>(conn)
+        .optional()
+}
+
+/// Flag a post or a user for the moderators.
+pub async fn create_report(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    body: web::Json<NewReport>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut report = body.into_inner();
+    report.reporter_id = Some(claims.id);
+    report.reason = report.reason.trim().to_lowercase();
+    report.details = report
+        .details
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty());
+
+    if !REPORT_REASONS.contains(&report.reason.as_str()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("Reason must be one of: {}", REPORT_REASONS.join(", "))
+        }));
+    }
+    if report
+        .details
+        .as_ref()
+        .is_some_and(|d| d.chars().count() > 1000)
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "Details must be at most 1000 characters"
+        }));
+    }
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    // Only things the reporter can actually see can be reported
+    let target = match report.target_type.as_str() {
+        REPORT_TARGET_POST => match i32::try_from(report.target_id) {
+            Ok(post_id) => post_listing()
+                .filter(viewable_by(claims.id))
+                .filter(posts::id.eq(post_id))
+                .select(posts::userid)
+                .first::<i64>(&mut conn)
+                .optional(),
+            Err(_) => Ok(None),
+        },
+        REPORT_TARGET_USER => live_users()
+            .filter(users::id.eq(report.target_id))
+            .select(users::id)
+            .first::<i64>(&mut conn)
+            .optional(),
+        _ => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": "Target type must be post or user"
+            }));
+        }
+    };
+
+    match target {
+        Ok(Some(owner)) if owner == claims.id => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": "You cannot report yourself"
+            }));
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": format!("{} not found", report.target_type)
+            }));
+        }
+        Err(e) => return db_error(e),
+    }
+
+    match diesel::insert_into(reports::table)
+        .values(&report)
+        .get_result::<Report>(&mut conn)
+    {
+        Ok(created) => HttpResponse::Created().json(serde_json::json!({
+            "status": true,
+            "message": "Report submitted",
+            "report": created
+        })),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            HttpResponse::Conflict().json(serde_json::json!({
+                "status": false,
+                "message": "You already have an open report on this"
+            }))
+        }
+        Err(e) => db_error(e),
+    }
+}
+
+/// The moderation queue, oldest first. Filters: `status` (default open),
+/// `target_type`, `reason`.
+pub async fn list_reports(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_role(&mut conn, claims.id, &STAFF) {
+        return resp;
+    }
+
+    let page = query
+        .get("page")
+        .and_then(|p| p.parse::<i64>().ok())
+        .filter(|p| *p >= 1)
+        .unwrap_or(1);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .filter(|l| (1..=100).contains(l))
+        .unwrap_or(20);
+    let status = query
+        .get("status")
+        .cloned()
+        .unwrap_or_else(|| REPORT_STATUS_OPEN.to_string());
+
+    let filtered = || {
+        let mut listing = reports::table
+            .filter(reports::status.eq(status.clone()))
+            .into_boxed();
+        if let Some(target_type) = query.get("target_type") {
+            listing = listing.filter(reports::target_type.eq(target_type.clone()));
+        }
+        if let Some(reason) = query.get("reason") {
+            listing = listing.filter(reports::reason.eq(reason.clone()));
+        }
+        listing
+    };
+
+    let items = filtered()
+        .order(reports::id)
+        .limit(limit)
+        .offset((page - 1) * limit)
+        .load::<Report>(&mut conn);
+    let total = filtered().count().get_result::<i64>(&mut conn);
+
+    match items.and_then(|items| total.map(|total| (items, total))) {
+        Ok((items, total)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "reports": items,
+            "total_reports": total
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+/// Hide the post from everyone but its author.
+fn hide_post(conn: &mut PgConnection, post_id: i64) -> QueryResult<()> {
+    diesel::update(
+        posts::table
+            .filter(posts::id.eq(post_id as i32))
+            .filter(posts::hidden_at.is_null()),
+    )
+    .set(posts::hidden_at.eq(Some(Utc::now().naive_utc())))
+    .execute(conn)
+    .map(|_| ())
+}
+
+fn suspend_user(
+    conn: &mut PgConnection,
+    user_id: i64,
+    until: NaiveDateTime,
+    reason: &str,
+) -> QueryResult<()> {
+    // A ban outranks a suspension and is left alone
+    diesel::update(
+        users::table
+            .filter(users::id.eq(user_id))
+            .filter(users::status.ne(USER_STATUS_BANNED)),
+    )
+    .set((
+        users::status.eq(USER_STATUS_SUSPENDED),
+        users::suspended_until.eq(Some(until)),
+        users::suspension_reason.eq(Some(reason)),
+        users::status_changed_at.eq(Some(Utc::now().naive_utc())),
+    ))
+    .execute(conn)
+    .map(|_| ())
+}
+
+/// Settle an open report: `resolve`, `dismiss`, `hide_post` or `suspend_user`.
+/// Hiding or suspending also closes the other open reports on the same target.
+pub async fn decide_report(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+    body: web::Json<ModerationDecision>,
+) -> impl Responder {
+    let report_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_role(&mut conn, claims.id, &STAFF) {
+        return resp;
+    }
+
+    if !MODERATION_DECISIONS.contains(&body.action.as_str()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("Action must be one of: {}", MODERATION_DECISIONS.join(", "))
+        }));
+    }
+
+    let report = match reports::table
+        .filter(reports::id.eq(report_id))
+        .first::<Report>(&mut conn)
+        .optional()
+    {
+        Ok(Some(r)) if r.status == REPORT_STATUS_OPEN => r,
+        Ok(Some(r)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "status": false,
+                "message": format!("Report is already {}", r.status)
+            }));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": "Report not found"
+            }));
+        }
+        Err(e) => return db_error(e),
+    };
+
+    // What the decision acts on, and which open reports it settles
+    let (target_type, target_id) = match body.action.as_str() {
+        MODERATION_HIDE_POST if report.target_type != REPORT_TARGET_POST => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": "Only reported posts can be hidden"
+            }));
+        }
+        MODERATION_SUSPEND_USER if report.target_type == REPORT_TARGET_POST => {
+            match post_author(&mut conn, report.target_id) {
+                Ok(Some(author)) => (REPORT_TARGET_USER, author),
+                Ok(None) => {
+                    return HttpResponse::NotFound().json(serde_json::json!({
+                        "status": false,
+                        "message": "post not found"
+                    }));
+                }
+                Err(e) => return db_error(e),
+            }
+        }
+        MODERATION_SUSPEND_USER => (REPORT_TARGET_USER, report.target_id),
+        _ => (report.target_type.as_str(), report.target_id),
+    };
+
+    let mut suspension = None;
+    if body.action == MODERATION_SUSPEND_USER {
+        let days = match body.suspend_days {
+            Some(days) if (1..=365).contains(&days) => days,
+            _ => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "status": false,
+                    "message": "suspend_days must be between 1 and 365"
+                }));
+            }
+        };
+        match require_role(&mut conn, target_id, &[ROLE_USER]) {
+            Ok(_) => {}
+            Err(_) => {
+                return HttpResponse::Forbidden().json(serde_json::json!({
+                    "status": false,
+                    "message": "Only regular accounts can be suspended"
+                }));
+            }
+        }
+        let reason = body
+            .suspend_reason
+            .as_ref()
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty())
+            .unwrap_or_else(|| report.reason.clone());
+        suspension = Some((Utc::now().naive_utc() + Duration::days(days), reason));
+    }
+
+    let new_status = if body.action == MODERATION_DISMISS {
+        REPORT_STATUS_DISMISSED
+    } else {
+        REPORT_STATUS_RESOLVED
+    };
+    let acts_on_target = body.action == MODERATION_HIDE_POST || suspension.is_some();
+
+    let decided = conn.transaction::<_, DieselError, _>(|conn| {
+        match (body.action.as_str(), &suspension) {
+            (MODERATION_HIDE_POST, _) => hide_post(conn, target_id)?,
+            (_, Some((until, reason))) => suspend_user(conn, target_id, *until, reason)?,
+            _ => {}
+        }
+
+        let open_reports = || reports::table.filter(reports::status.eq(REPORT_STATUS_OPEN));
+        let settle = || {
+            (
+                reports::status.eq(new_status),
+                reports::resolved_at.eq(Some(Utc::now().naive_utc())),
+                reports::resolved_by.eq(Some(claims.id)),
+            )
+        };
+
+        let mut closed = diesel::update(open_reports().filter(reports::id.eq(report.id)))
+            .set(settle())
+            .execute(conn)?;
+        if closed == 0 {
+            // Another moderator got there first
+            return Err(DieselError::RollbackTransaction);
+        }
+        if acts_on_target {
+            closed += diesel::update(
+                open_reports()
+                    .filter(reports::target_type.eq(target_type))
+                    .filter(reports::target_id.eq(target_id)),
+            )
+            .set(settle())
+            .execute(conn)?;
+        }
+
+        diesel::insert_into(moderation_actions::table)
+            .values(&NewModerationAction {
+                moderator_id: Some(claims.id),
+                report_id: Some(report.id),
+                action: body.action.clone(),
+                target_type: target_type.to_string(),
+                target_id,
+                note: body.note.clone(),
+            })
+            .get_result::<ModerationAction>(conn)
+            .map(|action| (action, closed))
+    });
+
+    match decided {
+        Ok((action, closed)) => {
+            if body.action == MODERATION_HIDE_POST {
+                emit_post(&mut conn, STREAM_POST_DELETED, target_id as i32);
+            }
+            let mut entry = audit::event(
+                &req,
+                Some(claims.id),
+                AUDIT_MODERATION_DECISION,
+                Some((AUDIT_TARGET_REPORT, report.id)),
+            );
+            entry.before = Some(serde_json::json!({ "status": report.status }));
+            entry.after = Some(serde_json::json!({
+                "status": new_status,
+                "action": body.action,
+                "target_type": target_type,
+                "target_id": target_id,
+                "suspended_until": suspension.as_ref().map(|(until, _)| until),
+                "reports_closed": closed
+            }));
+            audit::record(&mut conn, entry);
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": format!("Report {}", new_status),
+                "action": action,
+                "reports_closed": closed
+            }))
+        }
+        Err(DieselError::RollbackTransaction) => HttpResponse::Conflict().json(serde_json::json!({
+            "status": false,
+            "message": "Report was already handled"
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+/// Past decisions, newest first. Filters: `moderator_id`, `target_type`, `target_id`.
+pub async fn list_moderation_actions(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_role(&mut conn, claims.id, &STAFF) {
+        return resp;
+    }
+
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .filter(|l| (1..=100).contains(l))
+        .unwrap_or(50);
+
+    let mut listing = moderation_actions::table.into_boxed();
+    if let Some(id) = query
+        .get("moderator_id")
+        .and_then(|v| v.parse::<i64>().ok())
+    {
+        listing = listing.filter(moderation_actions::moderator_id.eq(id));
+    }
+    if let Some(target_type) = query.get("target_type") {
+        listing = listing.filter(moderation_actions::target_type.eq(target_type.clone()));
+    }
+    if let Some(id) = query.get("target_id").and_then(|v| v.parse::<i64>().ok()) {
+        listing = listing.filter(moderation_actions::target_id.eq(id));
+    }
+    if let Some(before) = query.get("before").and_then(|b| b.parse::<i64>().ok()) {
+        listing = listing.filter(moderation_actions::id.lt(before));
+    }
+
+    match listing
+        .order(moderation_actions::id.desc())
+        .limit(limit)
+        .load::<ModerationAction>(&mut conn)
+    {
+        Ok(items) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "actions": items
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+/// Admins promote or demote accounts. The change is recorded like any other decision.
+pub async fn set_user_role(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+    body: web::Json<RoleUpdate>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_role(&mut conn, claims.id, &[ROLE_ADMIN]) {
+        return resp;
+    }
+
+    if !ROLES.contains(&body.role.as_str()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("Role must be one of: {}", ROLES.join(", "))
+        }));
+    }
+    if target_id == claims.id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "You cannot change your own role"
+        }));
+    }
+
+    let updated = conn.transaction::<_, DieselError, _>(|conn| {
+        let previous = live_users()
+            .filter(users::id.eq(target_id))
+            .select(users::role)
+            .first::<String>(conn)?;
+        diesel::update(users::table.filter(users::id.eq(target_id)))
+            .set(users::role.eq(&body.role))
+            .execute(conn)?;
+        diesel::insert_into(moderation_actions::table)
+            .values(&NewModerationAction {
+                moderator_id: Some(claims.id),
+                report_id: None,
+                action: MODERATION_SET_ROLE.to_string(),
+                target_type: REPORT_TARGET_USER.to_string(),
+                target_id,
+                note: Some(format!("{} -> {}", previous, body.role)),
+            })
+            .execute(conn)?;
+        Ok(previous)
+    });
+
+    match updated {
+        Ok(previous) => {
+            let mut entry = audit::event(
+                &req,
+                Some(claims.id),
+                AUDIT_ROLE_CHANGED,
+                Some((AUDIT_TARGET_USER, target_id)),
+            );
+            entry.before = Some(serde_json::json!({ "role": previous }));
+            entry.after = Some(serde_json::json!({ "role": body.role }));
+            audit::record(&mut conn, entry);
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Role updated",
+                "role": body.role
+            }))
+        }
+        Err(DieselError::NotFound) => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "User not found"
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+/// Set an account's status. Anything but `active` also revokes the tokens the
+/// user already holds; `suspended` needs a future `suspended_until`.
+pub async fn set_user_status(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+    body: web::Json<StatusUpdate>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_role(&mut conn, claims.id, &[ROLE_ADMIN]) {
+        return resp;
+    }
+
+    let status = body.status.trim().to_lowercase();
+    if !USER_STATUSES.contains(&status.as_str()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("Status must be one of: {}", USER_STATUSES.join(", "))
+        }));
+    }
+    if target_id == claims.id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "You cannot change your own status"
+        }));
+    }
+    let now = Utc::now().naive_utc();
+    let suspended_until = if status == USER_STATUS_SUSPENDED {
+        match body.suspended_until {
+            Some(until) if until > now => Some(until),
+            _ => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "status": false,
+                    "message": "suspended_until must be in the future"
+                }));
+            }
+        }
+    } else {
+        None
+    };
+    let reason = body
+        .reason
+        .as_ref()
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty());
+
+    let updated = conn.transaction::<_, DieselError, _>(|conn| {
+        let previous = live_users()
+            .filter(users::id.eq(target_id))
+            .select((
+                users::status,
+                users::suspended_until,
+                users::suspension_reason,
+            ))
+            .first::<(String, Option<NaiveDateTime>, Option<String>)>(conn)?;
+        let target = users::table.filter(users::id.eq(target_id));
+        if status == USER_STATUS_ACTIVE {
+            diesel::update(target)
+                .set((
+                    users::status.eq(&status),
+                    users::suspended_until.eq(None::<NaiveDateTime>),
+                    users::suspension_reason.eq(None::<String>),
+                ))
+                .execute(conn)?;
+        } else {
+            diesel::update(target)
+                .set((
+                    users::status.eq(&status),
+                    users::suspended_until.eq(suspended_until),
+                    users::suspension_reason.eq(&reason),
+                    users::status_changed_at.eq(Some(now)),
+                ))
+                .execute(conn)?;
+        }
+        let note = match &reason {
+            Some(reason) => format!("{} -> {}: {}", previous.0, status, reason),
+            None => format!("{} -> {}", previous.0, status),
+        };
+        diesel::insert_into(moderation_actions::table)
+            .values(&NewModerationAction {
+                moderator_id: Some(claims.id),
+                report_id: None,
+                action: MODERATION_SET_STATUS.to_string(),
+                target_type: REPORT_TARGET_USER.to_string(),
+                target_id,
+                note: Some(note),
+            })
+            .execute(conn)?;
+        Ok(previous)
+    });
+
+    match updated {
+        Ok((previous, previous_until, previous_reason)) => {
+            let mut entry = audit::event(
+                &req,
+                Some(claims.id),
+                AUDIT_STATUS_CHANGED,
+                Some((AUDIT_TARGET_USER, target_id)),
+            );
+            (entry.before, entry.after) = audit::changes(
+                serde_json::json!({
+                    "status": previous,
+                    "suspended_until": previous_until,
+                    "suspension_reason": previous_reason
+                }),
+                serde_json::json!({
+                    "status": status,
+                    "suspended_until": suspended_until,
+                    "suspension_reason": if status == USER_STATUS_ACTIVE { None } else { reason.clone() }
+                }),
+            );
+            audit::record(&mut conn, entry);
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Status updated",
+                "account_status": status,
+                "suspended_until": suspended_until
+            }))
+        }
+        Err(DieselError::NotFound) => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "User not found"
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+/// Give a user a new password chosen by an admin, e.g. after an account
+/// takeover. The same rules apply as for a user's own change.
+pub async fn reset_user_password(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+    body: web::Json<PasswordReset>,
+) -> impl Responder {
+    let target_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = require_role(&mut conn, claims.id, &[ROLE_ADMIN]) {
+        return resp;
+    }
+    if target_id == claims.id {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "Use /api/changePassword for your own password"
+        }));
+    }
+
+    // The reset and its moderation record land together or not at all
+    let reset = conn.transaction::<_, PasswordError, _>(|conn| {
+        set_password(conn, target_id, &body.new_password)?;
+        diesel::insert_into(moderation_actions::table)
+            .values(&NewModerationAction {
+                moderator_id: Some(claims.id),
+                report_id: None,
+                action: MODERATION_RESET_PASSWORD.to_string(),
+                target_type: REPORT_TARGET_USER.to_string(),
+                target_id,
+                note: None,
+            })
+            .execute(conn)?;
+        Ok(())
+    });
+    if let Err(e) = reset {
+        return e.to_response();
+    }
+    audit::record(
+        &mut conn,
+        audit::event(
+            &req,
+            Some(claims.id),
+            AUDIT_PASSWORD_CHANGED,
+            Some((AUDIT_TARGET_USER, target_id)),
+        ),
+    );
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": true,
+        "message": "Password reset"
+    }))
+}
@@ -0,0 +1,221 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    db::Pool,
+    models::user::{
+        MarkReadRequest, NOTIFICATION_KINDS, Notification, NotificationPreference, PreferenceUpdate,
+    },
+    schema::{notification_preferences, notifications},
+    utils::auth::require_claims,
+    utils::notifications::preference_for,
+};
+
+/// Newest first. `before` takes the last id seen for the next page,
+/// `unread=true` limits the list to unread notifications.
+pub async fn list_notifications(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .filter(|l| (1..=100).contains(l))
+        .unwrap_or(20);
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let mut listing = notifications::table
+        .filter(notifications::user_id.eq(claims.id))
+        .into_boxed();
+    if let Some(before) = query.get("before").and_then(|b| b.parse::<i64>().ok()) {
+        listing = listing.filter(notifications::id.lt(before));
+    }
+    if query.get("unread").map(String::as_str) == Some("true") {
+        listing = listing.filter(notifications::read_at.is_null());
+    }
+
+    let result = listing
+        .order(notifications::id.desc())
+        .limit(limit)
+        .load::<Notification>(&mut conn);
+
+    match result {
+        Ok(items) => {
+            let next_before = if items.len() as i64 == limit {
+                items.last().map(|n| n.id)
+            } else {
+                None
+            };
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "notifications": items,
+                "next_before": next_before
+            }))
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+pub async fn unread_notification_count(req: HttpRequest, pool: web::Data<Pool>) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let unread = notifications::table
+        .filter(notifications::user_id.eq(claims.id))
+        .filter(notifications::read_at.is_null())
+        .count()
+        .get_result::<i64>(&mut conn)
+        .unwrap_or(0);
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": true,
+        "unread": unread
+    }))
+}
+
+pub async fn mark_notifications_read(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    body: web::Json<MarkReadRequest>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let mut target = diesel::update(notifications::table)
+        .filter(notifications::user_id.eq(claims.id))
+        .filter(notifications::read_at.is_null())
+        .into_boxed();
+    if let Some(ids) = &body.ids {
+        target = target.filter(notifications::id.eq_any(ids));
+    }
+
+    match target
+        .set(notifications::read_at.eq(Some(Utc::now().naive_utc())))
+        .execute(&mut conn)
+    {
+        Ok(count) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Notifications marked as read",
+            "updated": count
+        })),
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to mark notifications as read",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Effective settings for every notification kind.
+pub async fn get_notification_preferences(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let preferences: QueryResult<Vec<NotificationPreference>> = NOTIFICATION_KINDS
+        .iter()
+        .map(|kind| preference_for(&mut conn, claims.id, kind))
+        .collect();
+
+    match preferences {
+        Ok(preferences) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "preferences": preferences
+        })),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Change the channels for one kind; fields left out keep their current value.
+pub async fn update_notification_preferences(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    body: web::Json<PreferenceUpdate>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    if !NOTIFICATION_KINDS.contains(&body.kind.as_str()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("Kind must be one of: {}", NOTIFICATION_KINDS.join(", "))
+        }));
+    }
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let saved = preference_for(&mut conn, claims.id, &body.kind).and_then(|current| {
+        let preference = NotificationPreference {
+            in_app: body.in_app.unwrap_or(current.in_app),
+            email: body.email.unwrap_or(current.email),
+            push: body.push.unwrap_or(current.push),
+            ..current
+        };
+        diesel::insert_into(notification_preferences::table)
+            .values(&preference)
+            .on_conflict((
+                notification_preferences::user_id,
+                notification_preferences::kind,
+            ))
+            .do_update()
+            .set((
+                notification_preferences::in_app.eq(preference.in_app),
+                notification_preferences::email.eq(preference.email),
+                notification_preferences::push.eq(preference.push),
+            ))
+            .get_result::<NotificationPreference>(&mut conn)
+    });
+
+    match saved {
+        Ok(preference) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Preferences updated",
+            "preference": preference
+        })),
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to update preferences",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
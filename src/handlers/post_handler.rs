@@ -1,16 +1,34 @@
-use actix_multipart::Multipart;
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
-use futures_util::TryStreamExt as _;
 use serde::Serialize;
-use std::{fs, path::Path};
+use validator::{Validate, ValidationError};
 
 use crate::{
     db::Pool,
-    models::user::{NewPost, Post, PostData, PostWithUser},
-    schema::{posts, users},
-    utils::auth::Claims,
-    utils::{img_upload::save_multiple_images, validation::Validator},
+    handlers::post_image_handler::{load_post_images, new_post_images, to_post_data},
+    handlers::revision_handler::record_revision,
+    handlers::tag_handler::{mention_events, mentioned_users, sync_post_entities},
+    models::user::{
+        AUDIT_POST_DELETED, AUDIT_TARGET_POST, NewPost, POST_STATUS_ARCHIVED, POST_STATUS_DRAFT,
+        POST_STATUS_PUBLISHED, POST_STATUS_SCHEDULED, Post, PostData, PostForm, PostImage,
+        PostUpdateForm, PostWithUser,
+    },
+    schema::{follows, post_images, posts, uploads, users},
+    utils::audit,
+    utils::auth::{Claims, require_claims},
+    utils::entities::parse_entities,
+    utils::notifications::publish_all,
+    utils::post_access::{PostListing, listable_by, post_listing, viewable_by},
+    utils::soft_delete::{live_posts, purge_at},
+    utils::stream_hub::{STREAM_POST_CREATED, STREAM_POST_DELETED, STREAM_POST_UPDATED, emit_post},
+    utils::{
+        img_upload::{POST_DIR, save_multiple_images},
+        multipart_form::MultipartForm,
+        upload::remove_stored_files,
+        validation::{Validator, validation_response},
+    },
 };
 
 #[derive(Serialize)]
@@ -30,7 +48,7 @@ struct PostsListResponse {
 pub async fn upload_post(
     req: HttpRequest,
     pool: web::Data<Pool>,
-    mut payload: Multipart,
+    payload: MultipartForm<PostForm>,
 ) -> impl Responder {
     let user_claims = if let Some(claims) = req.extensions().get::<Claims>() {
         claims.clone()
@@ -41,64 +59,57 @@ pub async fn upload_post(
         }));
     };
 
-    let mut name_field = String::new();
-    let mut description_field = String::new();
-    let mut files: Vec<(Vec<u8>, String)> = Vec::new();
-
-    while let Ok(Some(mut field)) = payload.try_next().await {
-        let field_name = field.name().to_string();
-
-        if field_name == "postImgs" {
-            let cd = field.content_disposition();
-            let filename = cd.get_filename().unwrap_or("unknown.jpg").to_string();
-
-            if let Err(e) = Validator::validate_image_type(&filename) {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "status": false,
-                    "message": e
-                }));
-            }
-
-            let mut bytes = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
-                bytes.extend_from_slice(&chunk);
-            }
-
-            if bytes.len() > 3 * 1024 * 1024 {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "status": false,
-                    "message": "File size should be less than 3MB"
-                }));
-            }
-
-            files.push((bytes, filename));
+    let MultipartForm {
+        fields: mut form,
+        mut files,
+    } = payload;
+    form.visibility = form.visibility.trim().to_string();
+    form.status = form.status.trim().to_string();
+    form.publish_at = form
+        .publish_at
+        .map(|ts| ts.trim().to_string())
+        .filter(|ts| !ts.is_empty());
+    let files = files.take("postImgs");
+
+    let mut errors = form.validate().err().unwrap_or_default();
+    let filenames_only: Vec<String> = files.iter().map(|f| f.original_name.clone()).collect();
+    if let Err(e) = Validator::validate_post_images(&filenames_only) {
+        let code = if files.is_empty() {
+            "required"
         } else {
-            let mut data = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
-                data.extend_from_slice(&chunk);
-            }
-            let val = String::from_utf8_lossy(&data).to_string();
-            match field_name.as_str() {
-                "name" => name_field = val,
-                "description" => description_field = val,
-                _ => {}
-            }
-        }
+            "invalid_image_type"
+        };
+        errors.add(
+            "postImgs",
+            ValidationError::new(code).with_message(e.into()),
+        );
     }
-
-    if let Err(e) = Validator::validate_post_name(&name_field) {
-        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
+    if form.status == POST_STATUS_DRAFT && form.publish_at.is_some() {
+        errors.add(
+            "publish_at",
+            ValidationError::new("draft_with_publish_at").with_message(
+                "A draft can't be scheduled; leave out publish_at or the draft status".into(),
+            ),
+        );
     }
-    if let Err(e) = Validator::validate_post_description(&description_field) {
-        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
+    if !errors.is_empty() {
+        return validation_response(&errors);
     }
 
-    let filenames_only: Vec<String> = files.iter().map(|(_, name)| name.clone()).collect();
-    if let Err(e) = Validator::validate_post_images(&filenames_only) {
-        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
-    }
+    // A publish_at queues the post instead of publishing it right away
+    let publish_at = form
+        .publish_at
+        .as_deref()
+        .and_then(|ts| Validator::validate_publish_at(ts).ok());
+    let status_field = if publish_at.is_some() {
+        POST_STATUS_SCHEDULED.to_string()
+    } else {
+        form.status
+    };
+    let (name_field, description_field, visibility_field) =
+        (form.name, form.description, form.visibility);
 
-    let saved_filenames = match save_multiple_images(files) {
+    let saved_images = match save_multiple_images(files) {
         Ok(v) => v,
         Err(e) => {
             return HttpResponse::InternalServerError()
@@ -106,30 +117,47 @@ pub async fn upload_post(
         }
     };
 
-    let imgs_db: Vec<Option<String>> = saved_filenames.into_iter().map(Some).collect();
+    let mut conn = pool.get().expect("DB connection error");
+
+    let stored_keys: Vec<String> = saved_images
+        .iter()
+        .map(|img| img.storage_key.clone())
+        .collect();
 
     let new_post = NewPost {
         userid: user_claims.id,
         name: name_field,
         description: description_field,
-        imgs: imgs_db,
+        published_at: (status_field == POST_STATUS_PUBLISHED).then(|| Utc::now().naive_utc()),
+        visibility: visibility_field,
+        status: status_field,
+        publish_at,
     };
 
-    let mut conn = pool.get().expect("DB connection error");
+    let inserted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(uploads::table)
+            .values(&saved_images)
+            .execute(conn)?;
+        let post = diesel::insert_into(posts::table)
+            .values(&new_post)
+            .get_result::<Post>(conn)?;
+        let images = diesel::insert_into(post_images::table)
+            .values(&new_post_images(post.id, 0, &saved_images))
+            .get_results::<PostImage>(conn)?;
+        record_revision(conn, &post, &images, post.userid)?;
+        let mentioned = sync_post_entities(conn, post.id, &post.description)?;
+        Ok((post, images, mentioned))
+    });
+
+    match inserted {
+        Ok((post, images, mentioned)) => {
+            let events = mention_events(&mut conn, post.id, post.userid, &mentioned);
+            publish_all(&mut conn, events.unwrap_or_default());
+            if post.status == POST_STATUS_PUBLISHED {
+                emit_post(&mut conn, STREAM_POST_CREATED, post.id);
+            }
 
-    match diesel::insert_into(posts::table)
-        .values(&new_post)
-        .get_result::<Post>(&mut conn)
-    {
-        Ok(post) => {
-            let post_data = PostData {
-                id: post.id,
-                userid: post.userid,
-                name: post.name,
-                description: post.description,
-                imgs: post.imgs,
-                created_at: post.created_at,
-            };
+            let post_data = to_post_data(post, images);
 
             HttpResponse::Created().json(PostResponse {
                 status: true,
@@ -139,6 +167,7 @@ pub async fn upload_post(
         }
         Err(e) => {
             eprintln!("Database insert failed: {}", e);
+            remove_stored_files(POST_DIR, &stored_keys);
             HttpResponse::InternalServerError().json(serde_json::json!({
                 "status": false,
                 "message": "Failed to insert post",
@@ -148,10 +177,86 @@ pub async fn upload_post(
     }
 }
 
+#[derive(Queryable)]
+struct PostRow {
+    id: i32,
+    userid: i64,
+    firstname: String,
+    lastname: String,
+    email: String,
+    profile: String,
+    name: String,
+    description: String,
+    visibility: String,
+    status: String,
+    published_at: Option<NaiveDateTime>,
+    created_at: Option<NaiveDateTime>,
+    updated_at: Option<NaiveDateTime>,
+}
+
+/// Run a listing query and attach each post's images.
+pub fn load_posts_with_users(
+    conn: &mut PgConnection,
+    query: PostListing<'_>,
+) -> QueryResult<Vec<PostWithUser>> {
+    let results = query
+        .select((
+            posts::id,
+            posts::userid,
+            users::firstname,
+            users::lastname,
+            users::email,
+            users::profile,
+            posts::name,
+            posts::description,
+            posts::visibility,
+            posts::status,
+            posts::published_at,
+            posts::created_at,
+            posts::updated_at,
+        ))
+        .load::<PostRow>(conn)?;
+
+    let post_ids: Vec<i32> = results.iter().map(|row| row.id).collect();
+    let mut images_by_post = load_post_images(conn, &post_ids)?;
+
+    Ok(results
+        .into_iter()
+        .map(|row| {
+            let images = images_by_post.remove(&row.id).unwrap_or_default();
+            PostWithUser {
+                entities: parse_entities(&row.description),
+                id: row.id,
+                user_id: row.userid,
+                firstname: row.firstname,
+                lastname: row.lastname,
+                email: row.email,
+                profile: row.profile,
+                name: row.name,
+                imgs: images.iter().map(|img| img.storage_key.clone()).collect(),
+                images,
+                description: row.description,
+                visibility: row.visibility,
+                status: row.status,
+                published_at: row.published_at,
+                created_at: row.created_at,
+                edited: row.updated_at.is_some(),
+                updated_at: row.updated_at,
+            }
+        })
+        .collect())
+}
+
 pub async fn get_all_posts(
+    req: HttpRequest,
     pool: web::Data<Pool>,
     query: web::Query<std::collections::HashMap<String, String>>,
 ) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
     let page = query
         .get("page")
         .and_then(|p| p.parse::<i64>().ok())
@@ -164,58 +269,15 @@ pub async fn get_all_posts(
 
     let mut conn = pool.get().expect("DB connection error");
 
-    let results = posts::table
-        .inner_join(users::table.on(posts::userid.eq(users::id)))
-        .order(posts::created_at.desc())
+    let listing = post_listing()
+        .filter(listable_by(claims.id))
+        .order((posts::published_at.desc(), posts::id.desc()))
         .limit(limit)
-        .offset(offset)
-        .select((
-            posts::id,
-            posts::userid,
-            users::firstname,
-            users::lastname,
-            users::email,
-            users::profile,
-            posts::name,
-            posts::imgs,
-            posts::description,
-            posts::created_at,
-        ))
-        .load::<(
-            i32,
-            i64,
-            String,
-            String,
-            String,
-            String,
-            String,
-            Vec<Option<String>>,
-            String,
-            Option<chrono::NaiveDateTime>,
-        )>(&mut conn)
-        .unwrap_or_default();
-
-    let posts_list: Vec<PostWithUser> = results
-        .into_iter()
-        .map(
-            |(pid, userid, fname, lname, email, profile, name, imgs, desc, created_at)| {
-                PostWithUser {
-                    id: pid,
-                    user_id: userid,
-                    firstname: fname,
-                    lastname: lname,
-                    email,
-                    profile,
-                    name,
-                    imgs: imgs.into_iter().filter_map(|x| x).collect(),
-                    description: desc,
-                    created_at,
-                }
-            },
-        )
-        .collect();
+        .offset(offset);
+    let posts_list = load_posts_with_users(&mut conn, listing).unwrap_or_default();
 
-    let total_count = posts::table
+    let total_count = post_listing()
+        .filter(listable_by(claims.id))
         .count()
         .get_result::<i64>(&mut conn)
         .unwrap_or(0);
@@ -227,29 +289,130 @@ pub async fn get_all_posts(
     })
 }
 
-pub async fn get_post_by_id(pool: web::Data<Pool>, path: web::Path<i32>) -> impl Responder {
+#[derive(Serialize)]
+struct FeedResponse {
+    status: bool,
+    posts: Vec<PostWithUser>,
+    next_cursor: Option<String>,
+}
+
+/// Keyset cursor: `<published_at in microseconds>_<post id>` of the last post seen.
+fn parse_cursor(cursor: &str) -> Option<(NaiveDateTime, i32)> {
+    let (micros, id) = cursor.split_once('_')?;
+    let ts = chrono::DateTime::from_timestamp_micros(micros.parse().ok()?)?.naive_utc();
+    Some((ts, id.parse().ok()?))
+}
+
+fn make_cursor(post: &PostWithUser) -> Option<String> {
+    let ts = post.published_at?;
+    Some(format!("{}_{}", ts.and_utc().timestamp_micros(), post.id))
+}
+
+/// Posts from the users the caller follows, newest first.
+/// Pass `cursor` from the previous response to get the next page.
+pub async fn get_feed(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .unwrap_or(10)
+        .clamp(1, 50);
+
+    let cursor = match query.get("cursor") {
+        Some(raw) => match parse_cursor(raw) {
+            Some(c) => Some(c),
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "status": false,
+                    "message": "Invalid cursor"
+                }));
+            }
+        },
+        None => None,
+    };
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let followee_ids = follows::table
+        .filter(follows::follower_id.eq(claims.id))
+        .select(follows::followee_id);
+
+    let mut listing = post_listing()
+        .filter(listable_by(claims.id))
+        .filter(posts::userid.eq_any(followee_ids))
+        .order((posts::published_at.desc(), posts::id.desc()))
+        .limit(limit);
+
+    if let Some((ts, last_id)) = cursor {
+        listing = listing.filter(
+            posts::published_at
+                .lt(ts)
+                .or(posts::published_at.eq(ts).and(posts::id.lt(last_id))),
+        );
+    }
+
+    match load_posts_with_users(&mut conn, listing) {
+        Ok(posts_list) => {
+            let next_cursor = if posts_list.len() as i64 == limit {
+                posts_list.last().and_then(make_cursor)
+            } else {
+                None
+            };
+
+            HttpResponse::Ok().json(FeedResponse {
+                status: true,
+                posts: posts_list,
+                next_cursor,
+            })
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+pub async fn get_post_by_id(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+) -> impl Responder {
     let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
     let mut conn = pool.get().expect("DB connection error");
 
-    let post_result = posts::table
+    // Posts the caller may not see are reported as missing
+    let post_result = post_listing()
         .filter(posts::id.eq(post_id))
+        .filter(viewable_by(claims.id))
+        .select(posts::all_columns)
         .first::<Post>(&mut conn)
         .optional();
 
     match post_result {
         Ok(Some(post)) => {
-            let post_data = PostData {
-                id: post.id,
-                userid: post.userid,
-                name: post.name,
-                description: post.description,
-                imgs: post.imgs,
-                created_at: post.created_at,
-            };
+            let images = load_post_images(&mut conn, &[post.id])
+                .map(|mut m| m.remove(&post.id).unwrap_or_default())
+                .unwrap_or_default();
 
             HttpResponse::Ok().json(serde_json::json!({
                 "status": true,
-                "post": post_data
+                "post": to_post_data(post, images)
             }))
         }
         Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
@@ -267,17 +430,33 @@ pub async fn get_post_by_id(pool: web::Data<Pool>, path: web::Path<i32>) -> impl
     }
 }
 
-pub async fn delete_post(pool: web::Data<Pool>, path: web::Path<i32>) -> impl Responder {
+/// Move a post to the trash. Its images stay on disk until the purge job
+/// removes the post for good, so it can be restored in the meantime.
+pub async fn delete_post(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+) -> impl Responder {
     let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
     let mut conn = pool.get().expect("DB connection error");
 
-    let post_result = posts::table
+    let post_result = live_posts()
         .filter(posts::id.eq(post_id))
         .first::<Post>(&mut conn)
         .optional();
 
-    let post = match post_result {
-        Ok(Some(p)) => p,
+    match post_result {
+        Ok(Some(p)) if p.userid != claims.id => {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "status": false,
+                "message": "You can only delete your own posts"
+            }));
+        }
+        Ok(Some(_)) => {}
         Ok(None) => {
             return HttpResponse::NotFound().json(serde_json::json!({
                 "status": false,
@@ -294,23 +473,28 @@ pub async fn delete_post(pool: web::Data<Pool>, path: web::Path<i32>) -> impl Re
         }
     };
 
-    // Delete images from disk
-    for img_opt in post.imgs {
-        if let Some(img) = img_opt {
-            let file_path = Path::new("files/userPost").join(&img);
-            if file_path.exists() {
-                if let Err(e) = fs::remove_file(&file_path) {
-                    eprintln!("Failed to delete post image {}: {}", img, e);
-                }
-            }
+    let deleted_at = Utc::now().naive_utc();
+    match diesel::update(live_posts().filter(posts::id.eq(post_id)))
+        .set(posts::deleted_at.eq(Some(deleted_at)))
+        .execute(&mut conn)
+    {
+        Ok(count) if count > 0 => {
+            emit_post(&mut conn, STREAM_POST_DELETED, post_id);
+            let mut entry = audit::event(
+                &req,
+                Some(claims.id),
+                AUDIT_POST_DELETED,
+                Some((AUDIT_TARGET_POST, post_id.into())),
+            );
+            entry.before = Some(serde_json::json!({ "deleted_at": null }));
+            entry.after = Some(serde_json::json!({ "deleted_at": deleted_at }));
+            audit::record(&mut conn, entry);
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Post moved to trash",
+                "restore_until": purge_at(deleted_at)
+            }))
         }
-    }
-
-    match diesel::delete(posts::table.filter(posts::id.eq(post_id))).execute(&mut conn) {
-        Ok(count) if count > 0 => HttpResponse::Ok().json(serde_json::json!({
-            "status": true,
-            "message": "Post deleted successfully"
-        })),
         Ok(_) => HttpResponse::BadRequest().json(serde_json::json!({
             "status": false,
             "message": "Cannot delete the post"
@@ -330,7 +514,7 @@ pub async fn update_post(
     req: HttpRequest,
     pool: web::Data<Pool>,
     path: web::Path<i32>,
-    mut payload: Multipart,
+    payload: MultipartForm<PostUpdateForm>,
 ) -> impl Responder {
     let post_id = path.into_inner();
 
@@ -345,7 +529,7 @@ pub async fn update_post(
 
     let mut conn = pool.get().expect("DB connection error");
 
-    let existing_post = posts::table
+    let existing_post = live_posts()
         .filter(posts::id.eq(post_id))
         .first::<Post>(&mut conn)
         .optional();
@@ -375,163 +559,273 @@ pub async fn update_post(
         }));
     }
 
-    let mut name_field = String::new();
-    let mut description_field = String::new();
-    let mut new_files: Vec<(Vec<u8>, String)> = Vec::new();
-    let mut delete_imgs: Vec<String> = Vec::new();
+    let MultipartForm {
+        fields: mut form,
+        mut files,
+    } = payload;
+    let new_files = files.take("postImgs");
+    // Empty text fields are left as they are
+    let present = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    form.name = present(form.name);
+    form.description = present(form.description);
+    form.visibility = present(form.visibility);
+    form.delete_imgs = form
+        .delete_imgs
+        .into_iter()
+        .filter_map(|key| present(Some(key)))
+        .collect();
 
-    while let Ok(Some(mut field)) = payload.try_next().await {
-        let field_name = field.name().to_string();
+    let current_images = match load_post_images(&mut conn, &[post_id]) {
+        Ok(mut m) => m.remove(&post_id).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    };
+
+    // Handle image deletion; files and their uploads rows stay because older revisions still show them
+    for img_to_delete in &form.delete_imgs {
+        // Only names stored on this post may be removed
+        if !current_images
+            .iter()
+            .any(|img| &img.storage_key == img_to_delete)
+        {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": format!("Image not part of this post: {}", img_to_delete)
+            }));
+        }
+    }
 
-        if field_name == "postImgs" {
-            let cd = field.content_disposition();
-            let filename = cd.get_filename().unwrap_or("unknown.jpg").to_string();
+    // Update text fields
+    if let Err(errors) = form.validate() {
+        return validation_response(&errors);
+    }
+    if let Some(name) = form.name {
+        post.name = name;
+    }
+    if let Some(description) = form.description {
+        post.description = description;
+    }
+    if let Some(visibility) = form.visibility {
+        post.visibility = visibility;
+    }
 
-            if let Err(e) = Validator::validate_image_type(&filename) {
-                return HttpResponse::BadRequest().json(serde_json::json!({
+    // Save new images
+    let saved_images = if new_files.is_empty() {
+        Vec::new()
+    } else {
+        match save_multiple_images(new_files) {
+            Ok(saved_images) => saved_images,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
                     "status": false,
                     "message": e
                 }));
             }
+        }
+    };
+    let stored_keys: Vec<String> = saved_images
+        .iter()
+        .map(|img| img.storage_key.clone())
+        .collect();
+    let next_position = current_images
+        .iter()
+        .map(|img| img.position + 1)
+        .max()
+        .unwrap_or(0);
 
-            let mut bytes = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
-                bytes.extend_from_slice(&chunk);
-            }
+    // Perform update
+    let updated_result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        if !form.delete_imgs.is_empty() {
+            diesel::delete(
+                post_images::table
+                    .filter(post_images::post_id.eq(post_id))
+                    .filter(post_images::storage_key.eq_any(&form.delete_imgs)),
+            )
+            .execute(conn)?;
+        }
+        if !saved_images.is_empty() {
+            diesel::insert_into(uploads::table)
+                .values(&saved_images)
+                .execute(conn)?;
+            diesel::insert_into(post_images::table)
+                .values(&new_post_images(post_id, next_position, &saved_images))
+                .execute(conn)?;
+        }
+        let updated = diesel::update(posts::table.filter(posts::id.eq(post_id)))
+            .set((
+                posts::name.eq(&post.name),
+                posts::description.eq(&post.description),
+                posts::visibility.eq(&post.visibility),
+                posts::updated_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .get_result::<Post>(conn)?;
+        let images = load_post_images(conn, &[post_id])?
+            .remove(&post_id)
+            .unwrap_or_default();
+        record_revision(conn, &updated, &images, user_claims.id)?;
+        let mentioned = sync_post_entities(conn, post_id, &updated.description)?;
+        Ok((updated, images, mentioned))
+    });
 
-            if bytes.len() > 3 * 1024 * 1024 {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "status": false,
-                    "message": "File size should be less than 3MB"
-                }));
-            }
+    match updated_result {
+        Ok((updated_post, images, mentioned)) => {
+            let events = mention_events(&mut conn, post_id, user_claims.id, &mentioned);
+            publish_all(&mut conn, events.unwrap_or_default());
+            emit_post(&mut conn, STREAM_POST_UPDATED, post_id);
 
-            new_files.push((bytes, filename));
-        } else if field_name == "deleteImg" {
-            let mut data = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
-                data.extend_from_slice(&chunk);
-            }
-            let img_name = String::from_utf8_lossy(&data)
-                .to_string()
-                .trim()
-                .to_string();
-            if !img_name.is_empty() {
-                delete_imgs.push(img_name);
-            }
-        } else {
-            let mut data = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
-                data.extend_from_slice(&chunk);
-            }
-            let val = String::from_utf8_lossy(&data)
-                .to_string()
-                .trim()
-                .to_string();
-            match field_name.as_str() {
-                "name" => name_field = val,
-                "description" => description_field = val,
-                _ => {}
-            }
+            let post_data = to_post_data(updated_post, images);
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Post updated successfully",
+                "post": post_data
+            }))
+        }
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            remove_stored_files(POST_DIR, &stored_keys);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to update post",
+                "error": e.to_string()
+            }))
         }
     }
+}
 
-    // Handle image deletion
-    if !delete_imgs.is_empty() {
-        for img_to_delete in &delete_imgs {
-            let file_path = Path::new("files/userPost").join(img_to_delete);
-            if file_path.exists() {
-                if let Err(e) = fs::remove_file(&file_path) {
-                    eprintln!("Failed to delete image {}: {}", img_to_delete, e);
-                }
-            } else {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "status": false,
-                    "message": format!("File not found: {}", img_to_delete)
-                }));
-            }
+/// Publish one of the caller's drafts (or bring back an archived post).
+pub async fn publish_post(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    set_post_status(&req, &pool, path.into_inner(), POST_STATUS_PUBLISHED)
+}
 
-            post.imgs.retain(|img_opt| {
-                if let Some(img) = img_opt {
-                    img != img_to_delete
-                } else {
-                    true
-                }
-            });
-        }
-    }
+/// Hide one of the caller's posts from every reader but the author.
+pub async fn archive_post(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    set_post_status(&req, &pool, path.into_inner(), POST_STATUS_ARCHIVED)
+}
 
-    // Update text fields
-    if !name_field.is_empty() {
-        if let Err(e) = Validator::validate_post_name(&name_field) {
+fn set_post_status(req: &HttpRequest, pool: &Pool, post_id: i32, target: &str) -> HttpResponse {
+    let claims = match require_claims(req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let existing_post = live_posts()
+        .filter(posts::id.eq(post_id))
+        .filter(posts::userid.eq(claims.id))
+        .first::<Post>(&mut conn)
+        .optional();
+
+    let current = match existing_post {
+        Ok(Some(p)) if p.status == target => {
             return HttpResponse::BadRequest().json(serde_json::json!({
                 "status": false,
-                "message": e
+                "message": format!("Post is already {}", target)
             }));
         }
-        post.name = name_field;
-    }
-
-    if !description_field.is_empty() {
-        if let Err(e) = Validator::validate_post_description(&description_field) {
-            return HttpResponse::BadRequest().json(serde_json::json!({
+        Ok(Some(p)) => p,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
                 "status": false,
-                "message": e
+                "message": "Post not found"
             }));
         }
-        post.description = description_field;
-    }
-
-    // Save new images
-    if !new_files.is_empty() {
-        match save_multiple_images(new_files) {
-            Ok(saved_filenames) => {
-                for filename in saved_filenames {
-                    post.imgs.push(Some(filename));
-                }
-            }
-            Err(e) => {
-                return HttpResponse::InternalServerError().json(serde_json::json!({
-                    "status": false,
-                    "message": e
-                }));
-            }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
         }
-    }
+    };
 
-    // Perform update
-    let updated_result = diesel::update(posts::table.filter(posts::id.eq(post_id)))
+    // Keep the first publication time so republishing does not bump the post
+    let first_publication = current.published_at.is_none() && target == POST_STATUS_PUBLISHED;
+    let published_at = if first_publication {
+        Some(Utc::now().naive_utc())
+    } else {
+        current.published_at
+    };
+    let updated = diesel::update(posts::table.filter(posts::id.eq(post_id)))
         .set((
-            posts::name.eq(&post.name),
-            posts::description.eq(&post.description),
-            posts::imgs.eq(&post.imgs),
+            posts::status.eq(target),
+            posts::publish_at.eq(None::<NaiveDateTime>),
+            posts::published_at.eq(published_at),
         ))
         .get_result::<Post>(&mut conn);
 
-    match updated_result {
-        Ok(updated_post) => {
-            let post_data = PostData {
-                id: updated_post.id,
-                userid: updated_post.userid,
-                name: updated_post.name,
-                description: updated_post.description,
-                imgs: updated_post.imgs,
-                created_at: updated_post.created_at,
+    match updated {
+        Ok(post) => {
+            // Mentions in a draft are announced when it first goes out
+            if first_publication {
+                let events = mentioned_users(&mut conn, post.id)
+                    .and_then(|ids| mention_events(&mut conn, post.id, post.userid, &ids));
+                publish_all(&mut conn, events.unwrap_or_default());
+            }
+            let stream_event = if target == POST_STATUS_PUBLISHED {
+                STREAM_POST_CREATED
+            } else {
+                STREAM_POST_DELETED
             };
+            emit_post(&mut conn, stream_event, post.id);
+
+            let images = load_post_images(&mut conn, &[post.id])
+                .map(|mut m| m.remove(&post.id).unwrap_or_default())
+                .unwrap_or_default();
 
             HttpResponse::Ok().json(serde_json::json!({
                 "status": true,
-                "message": "Post updated successfully",
-                "post": post_data
+                "message": format!("Post {} successfully", target),
+                "post": to_post_data(post, images)
             }))
         }
         Err(e) => {
             eprintln!("Database update failed: {}", e);
             HttpResponse::InternalServerError().json(serde_json::json!({
                 "status": false,
-                "message": "Failed to update post",
+                "message": "Failed to update post status",
                 "error": e.to_string()
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_parses_micros_and_id() {
+        let (ts, id) = parse_cursor("1767225600123456_42").unwrap();
+        assert_eq!(id, 42);
+        assert_eq!(ts.and_utc().timestamp_micros(), 1_767_225_600_123_456);
+    }
+
+    #[test]
+    fn malformed_cursors_are_refused() {
+        for cursor in [
+            "", "123", "_42", "123_", "abc_42", "123_abc", "123_42_7", "1.5_42",
+        ] {
+            assert!(parse_cursor(cursor).is_none(), "{:?}", cursor);
+        }
+    }
+}
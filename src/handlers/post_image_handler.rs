@@ -0,0 +1,364 @@
+use actix_files::NamedFile;
+use actix_web::http::header::{CACHE_CONTROL, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::Utc;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{
+    db::Pool,
+    handlers::revision_handler::record_revision,
+    models::user::{
+        AltTextRequest, NewPostImage, NewUpload, Post, PostData, PostImage, ReorderImagesRequest,
+    },
+    schema::{post_images, post_revisions, posts},
+    utils::auth::{Claims, require_claims},
+    utils::entities::parse_entities,
+    utils::post_access::{post_listing, viewable_by},
+    utils::soft_delete::live_posts,
+    utils::{img_upload::POST_DIR, upload::image_dimensions},
+};
+
+/// Load the images of the given posts, grouped by post and in display order.
+pub fn load_post_images(
+    conn: &mut PgConnection,
+    post_ids: &[i32],
+) -> QueryResult<HashMap<i32, Vec<PostImage>>> {
+    let rows = post_images::table
+        .filter(post_images::post_id.eq_any(post_ids))
+        .order((post_images::post_id, post_images::position, post_images::id))
+        .load::<PostImage>(conn)?;
+
+    let mut grouped: HashMap<i32, Vec<PostImage>> = HashMap::new();
+    for img in rows {
+        grouped.entry(img.post_id).or_default().push(img);
+    }
+    Ok(grouped)
+}
+
+/// Build `post_images` rows for freshly stored files, appended after `start_position`.
+pub fn new_post_images(
+    post_id: i32,
+    start_position: i32,
+    stored: &[NewUpload],
+) -> Vec<NewPostImage> {
+    stored
+        .iter()
+        .enumerate()
+        .map(|(i, upload)| {
+            let dims = image_dimensions(&Path::new(POST_DIR).join(&upload.storage_key));
+            NewPostImage {
+                post_id,
+                storage_key: upload.storage_key.clone(),
+                position: start_position + i as i32,
+                width: dims.map(|(w, _)| w),
+                height: dims.map(|(_, h)| h),
+                mime: upload.mime.clone(),
+                bytes: upload.bytes,
+            }
+        })
+        .collect()
+}
+
+pub fn to_post_data(post: Post, images: Vec<PostImage>) -> PostData {
+    PostData {
+        entities: parse_entities(&post.description),
+        id: post.id,
+        userid: post.userid,
+        name: post.name,
+        description: post.description,
+        imgs: images.iter().map(|img| img.storage_key.clone()).collect(),
+        images,
+        visibility: post.visibility,
+        status: post.status,
+        publish_at: post.publish_at,
+        published_at: post.published_at,
+        created_at: post.created_at,
+        edited: post.updated_at.is_some(),
+        updated_at: post.updated_at,
+    }
+}
+
+/// Mark a post as edited now.
+pub fn touch_post(conn: &mut PgConnection, post_id: i32) -> QueryResult<Post> {
+    diesel::update(posts::table.filter(posts::id.eq(post_id)))
+        .set(posts::updated_at.eq(Some(Utc::now().naive_utc())))
+        .get_result::<Post>(conn)
+}
+
+/// Fetch a post and make sure the caller owns it.
+#[allow(clippy::result_large_err)]
+pub fn owned_post(
+    conn: &mut PgConnection,
+    post_id: i32,
+    claims: &Claims,
+) -> Result<Post, HttpResponse> {
+    let post = live_posts()
+        .filter(posts::id.eq(post_id))
+        .first::<Post>(conn)
+        .optional();
+
+    match post {
+        Ok(Some(p)) if p.userid == claims.id => Ok(p),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "status": false,
+            "message": "You can only update your own posts"
+        }))),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "Post not found"
+        }))),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            })))
+        }
+    }
+}
+
+pub async fn reorder_post_images(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    body: web::Json<ReorderImagesRequest>,
+) -> impl Responder {
+    let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    if let Err(resp) = owned_post(&mut conn, post_id, &claims) {
+        return resp;
+    }
+
+    let current: Vec<i32> = post_images::table
+        .filter(post_images::post_id.eq(post_id))
+        .select(post_images::id)
+        .load::<i32>(&mut conn)
+        .unwrap_or_default();
+
+    let mut requested = body.image_ids.clone();
+    let mut existing = current.clone();
+    requested.sort_unstable();
+    existing.sort_unstable();
+    if requested != existing {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "image_ids must list every image of the post exactly once"
+        }));
+    }
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        for (position, image_id) in body.image_ids.iter().enumerate() {
+            diesel::update(post_images::table.filter(post_images::id.eq(image_id)))
+                .set(post_images::position.eq(position as i32))
+                .execute(conn)?;
+        }
+        let post = touch_post(conn, post_id)?;
+        let images = load_post_images(conn, &[post_id])?
+            .remove(&post_id)
+            .unwrap_or_default();
+        record_revision(conn, &post, &images, claims.id)?;
+        Ok((post, images))
+    });
+
+    match result {
+        Ok((post, images)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Images reordered successfully",
+            "post": to_post_data(post, images)
+        })),
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to reorder images",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+pub async fn set_image_alt_text(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<AltTextRequest>,
+) -> impl Responder {
+    let (post_id, image_id) = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    let alt_text = body
+        .alt_text
+        .as_ref()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    if alt_text.as_ref().is_some_and(|t| t.chars().count() > 500) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "Alt text must be at most 500 characters"
+        }));
+    }
+
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = owned_post(&mut conn, post_id, &claims) {
+        return resp;
+    }
+
+    let updated = diesel::update(
+        post_images::table
+            .filter(post_images::id.eq(image_id))
+            .filter(post_images::post_id.eq(post_id)),
+    )
+    .set(post_images::alt_text.eq(&alt_text))
+    .get_result::<PostImage>(&mut conn)
+    .optional();
+
+    match updated {
+        Ok(Some(image)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Alt text updated successfully",
+            "image": image
+        })),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "Image not found"
+        })),
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to update image",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+pub async fn delete_post_image(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let (post_id, image_id) = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = owned_post(&mut conn, post_id, &claims) {
+        return resp;
+    }
+
+    // The file and its uploads row stay while older revisions still show it
+    let deleted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let image = diesel::delete(
+            post_images::table
+                .filter(post_images::id.eq(image_id))
+                .filter(post_images::post_id.eq(post_id)),
+        )
+        .get_result::<PostImage>(conn)
+        .optional()?;
+        if image.is_some() {
+            let post = touch_post(conn, post_id)?;
+            let images = load_post_images(conn, &[post_id])?
+                .remove(&post_id)
+                .unwrap_or_default();
+            record_revision(conn, &post, &images, claims.id)?;
+        }
+        Ok(image)
+    });
+
+    match deleted {
+        Ok(Some(_)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Image deleted successfully"
+        })),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "Image not found"
+        })),
+        Err(e) => {
+            eprintln!("Database delete failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to delete image",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Serve an image of a post the caller may view. Images only kept for older
+/// revisions are served to the author alone, who is the one who can see them.
+pub async fn get_post_image(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(i32, String)>,
+) -> impl Responder {
+    let (post_id, key) = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let author = post_listing()
+        .filter(viewable_by(claims.id))
+        .filter(posts::id.eq(post_id))
+        .select(posts::userid)
+        .first::<i64>(&mut conn)
+        .optional()
+        .unwrap_or(None);
+
+    let attached = match author {
+        Some(author) => {
+            let current = post_images::table
+                .filter(post_images::post_id.eq(post_id))
+                .filter(post_images::storage_key.eq(&key))
+                .count()
+                .get_result::<i64>(&mut conn)
+                .unwrap_or(0);
+            let in_history = author == claims.id
+                && post_revisions::table
+                    .filter(post_revisions::post_id.eq(post_id))
+                    .filter(post_revisions::images.contains(vec![Some(key.clone())]))
+                    .count()
+                    .get_result::<i64>(&mut conn)
+                    .unwrap_or(0)
+                    > 0;
+            current > 0 || in_history
+        }
+        None => false,
+    };
+    let file = if attached {
+        NamedFile::open(Path::new(POST_DIR).join(&key)).ok()
+    } else {
+        None
+    };
+
+    match file {
+        Some(file) => {
+            let mut response = file.into_response(&req);
+            response.headers_mut().insert(
+                CACHE_CONTROL,
+                HeaderValue::from_static("private, max-age=86400"),
+            );
+            response
+        }
+        None => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "Image not found"
+        })),
+    }
+}
@@ -0,0 +1,322 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::Utc;
+use diesel::dsl::max;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use std::path::Path;
+
+use crate::{
+    db::Pool,
+    handlers::post_image_handler::{load_post_images, owned_post, to_post_data},
+    handlers::tag_handler::{mention_events, sync_post_entities},
+    models::user::{
+        NewPostImage, NewPostRevision, Post, PostImage, PostRevision, RevisionDiffQuery,
+    },
+    schema::{post_images, post_revisions, posts, uploads},
+    utils::auth::require_claims,
+    utils::notifications::publish_all,
+    utils::stream_hub::{STREAM_POST_UPDATED, emit_post},
+    utils::{img_upload::POST_DIR, upload::image_dimensions},
+};
+
+/// Append the current state of `post` to its history.
+/// Call inside the transaction that changed the post so the two stay in step.
+/// The post row is locked first, so concurrent edits get consecutive
+/// revision numbers and each snapshot sees the text as committed before it.
+pub fn record_revision(
+    conn: &mut PgConnection,
+    post: &Post,
+    images: &[PostImage],
+    editor_id: i64,
+) -> QueryResult<PostRevision> {
+    let (name, description) = posts::table
+        .filter(posts::id.eq(post.id))
+        .select((posts::name, posts::description))
+        .for_update()
+        .first::<(String, String)>(conn)?;
+    let latest = post_revisions::table
+        .filter(post_revisions::post_id.eq(post.id))
+        .select(max(post_revisions::revision))
+        .first::<Option<i32>>(conn)?;
+
+    diesel::insert_into(post_revisions::table)
+        .values(&NewPostRevision {
+            post_id: post.id,
+            revision: latest.unwrap_or(0) + 1,
+            editor_id: Some(editor_id),
+            name,
+            description,
+            images: images
+                .iter()
+                .map(|img| Some(img.storage_key.clone()))
+                .collect(),
+        })
+        .get_result::<PostRevision>(conn)
+}
+
+fn revision_images(revision: &PostRevision) -> Vec<String> {
+    revision.images.iter().flatten().cloned().collect()
+}
+
+#[allow(clippy::result_large_err)]
+fn find_revision(
+    conn: &mut PgConnection,
+    post_id: i32,
+    revision: i32,
+) -> Result<PostRevision, HttpResponse> {
+    let found = post_revisions::table
+        .filter(post_revisions::post_id.eq(post_id))
+        .filter(post_revisions::revision.eq(revision))
+        .first::<PostRevision>(conn)
+        .optional();
+
+    match found {
+        Ok(Some(r)) => Ok(r),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": format!("Revision {} not found", revision)
+        }))),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            })))
+        }
+    }
+}
+
+pub async fn list_post_revisions(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = owned_post(&mut conn, post_id, &claims) {
+        return resp;
+    }
+
+    let revisions = post_revisions::table
+        .filter(post_revisions::post_id.eq(post_id))
+        .order(post_revisions::revision.desc())
+        .load::<PostRevision>(&mut conn);
+
+    match revisions {
+        Ok(revisions) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "revisions": revisions
+        })),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DiffChunk {
+    tag: &'static str,
+    value: String,
+}
+
+/// Word-level diff with neighbouring changes of the same kind merged.
+fn text_diff(old: &str, new: &str) -> Vec<DiffChunk> {
+    let mut chunks: Vec<DiffChunk> = Vec::new();
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let tag = match change.tag() {
+            ChangeTag::Equal => "equal",
+            ChangeTag::Delete => "delete",
+            ChangeTag::Insert => "insert",
+        };
+        match chunks.last_mut() {
+            Some(last) if last.tag == tag => last.value.push_str(change.value()),
+            _ => chunks.push(DiffChunk {
+                tag,
+                value: change.value().to_string(),
+            }),
+        }
+    }
+    chunks
+}
+
+pub async fn diff_post_revisions(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    query: web::Query<RevisionDiffQuery>,
+) -> impl Responder {
+    let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = owned_post(&mut conn, post_id, &claims) {
+        return resp;
+    }
+
+    let from = match find_revision(&mut conn, post_id, query.from) {
+        Ok(r) => r,
+        Err(resp) => return resp,
+    };
+    let to = match find_revision(&mut conn, post_id, query.to) {
+        Ok(r) => r,
+        Err(resp) => return resp,
+    };
+
+    let from_images = revision_images(&from);
+    let to_images = revision_images(&to);
+    let added: Vec<&String> = to_images
+        .iter()
+        .filter(|key| !from_images.contains(key))
+        .collect();
+    let removed: Vec<&String> = from_images
+        .iter()
+        .filter(|key| !to_images.contains(key))
+        .collect();
+    let kept_from: Vec<&String> = from_images
+        .iter()
+        .filter(|key| to_images.contains(key))
+        .collect();
+    let kept_to: Vec<&String> = to_images
+        .iter()
+        .filter(|key| from_images.contains(key))
+        .collect();
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": true,
+        "from": from.revision,
+        "to": to.revision,
+        "name": text_diff(&from.name, &to.name),
+        "description": text_diff(&from.description, &to.description),
+        "images": {
+            "added": added,
+            "removed": removed,
+            "reordered": kept_from != kept_to
+        }
+    }))
+}
+
+/// Put the post back to the content of an earlier revision.
+/// The restore itself is recorded as a new revision.
+pub async fn restore_post_revision(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let (post_id, revision) = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = owned_post(&mut conn, post_id, &claims) {
+        return resp;
+    }
+
+    let target = match find_revision(&mut conn, post_id, revision) {
+        Ok(r) => r,
+        Err(resp) => return resp,
+    };
+    let target_images = revision_images(&target);
+
+    // Images dropped since then are kept on disk for history; bail out if one went missing anyway
+    if let Some(missing) = target_images
+        .iter()
+        .find(|key| !Path::new(POST_DIR).join(key).exists())
+    {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "status": false,
+            "message": format!("Image no longer available: {}", missing)
+        }));
+    }
+
+    let restored = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let current = load_post_images(conn, &[post_id])?
+            .remove(&post_id)
+            .unwrap_or_default();
+
+        diesel::delete(
+            post_images::table
+                .filter(post_images::post_id.eq(post_id))
+                .filter(post_images::storage_key.ne_all(&target_images)),
+        )
+        .execute(conn)?;
+
+        for (position, key) in target_images.iter().enumerate() {
+            if current.iter().any(|img| &img.storage_key == key) {
+                diesel::update(post_images::table.filter(post_images::storage_key.eq(key)))
+                    .set(post_images::position.eq(position as i32))
+                    .execute(conn)?;
+                continue;
+            }
+
+            let upload = uploads::table
+                .filter(uploads::storage_key.eq(key))
+                .select((uploads::mime, uploads::bytes))
+                .first::<(String, i64)>(conn)
+                .optional()?;
+            let (mime, bytes) = upload.unwrap_or_else(|| ("image/jpeg".to_string(), 0));
+            let dims = image_dimensions(&Path::new(POST_DIR).join(key));
+            diesel::insert_into(post_images::table)
+                .values(&NewPostImage {
+                    post_id,
+                    storage_key: key.clone(),
+                    position: position as i32,
+                    width: dims.map(|(w, _)| w),
+                    height: dims.map(|(_, h)| h),
+                    mime,
+                    bytes,
+                })
+                .execute(conn)?;
+        }
+
+        let post = diesel::update(posts::table.filter(posts::id.eq(post_id)))
+            .set((
+                posts::name.eq(&target.name),
+                posts::description.eq(&target.description),
+                posts::updated_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .get_result::<Post>(conn)?;
+        let images = load_post_images(conn, &[post_id])?
+            .remove(&post_id)
+            .unwrap_or_default();
+        record_revision(conn, &post, &images, claims.id)?;
+        let mentioned = sync_post_entities(conn, post_id, &post.description)?;
+        Ok((post, images, mentioned))
+    });
+
+    match restored {
+        Ok((post, images, mentioned)) => {
+            let events = mention_events(&mut conn, post_id, claims.id, &mentioned);
+            publish_all(&mut conn, events.unwrap_or_default());
+            emit_post(&mut conn, STREAM_POST_UPDATED, post_id);
+
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": format!("Post restored to revision {}", revision),
+                "post": to_post_data(post, images)
+            }))
+        }
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to restore revision",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::{
+    db::Pool,
+    handlers::post_image_handler::{load_post_images, owned_post, to_post_data},
+    models::user::{
+        POST_STATUS_DRAFT, POST_STATUS_PUBLISHED, POST_STATUS_SCHEDULED, Post, PostData,
+        ScheduleRequest,
+    },
+    schema::posts,
+    utils::auth::require_claims,
+    utils::soft_delete::live_posts,
+    utils::validation::Validator,
+};
+
+fn with_images(conn: &mut PgConnection, post: Post) -> PostData {
+    let images = load_post_images(conn, &[post.id])
+        .map(|mut m| m.remove(&post.id).unwrap_or_default())
+        .unwrap_or_default();
+    to_post_data(post, images)
+}
+
+/// The caller's queued posts, next to go live first.
+pub async fn list_scheduled_posts(req: HttpRequest, pool: web::Data<Pool>) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let scheduled = live_posts()
+        .filter(posts::userid.eq(claims.id))
+        .filter(posts::status.eq(POST_STATUS_SCHEDULED))
+        .order((posts::publish_at.asc(), posts::id.asc()))
+        .load::<Post>(&mut conn);
+
+    let scheduled = match scheduled {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    };
+
+    let ids: Vec<i32> = scheduled.iter().map(|p| p.id).collect();
+    let mut images = load_post_images(&mut conn, &ids).unwrap_or_default();
+    let posts: Vec<PostData> = scheduled
+        .into_iter()
+        .map(|p| {
+            let imgs = images.remove(&p.id).unwrap_or_default();
+            to_post_data(p, imgs)
+        })
+        .collect();
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": true,
+        "posts": posts
+    }))
+}
+
+/// Queue a draft or move an already scheduled post to a new time.
+pub async fn reschedule_post(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    body: web::Json<ScheduleRequest>,
+) -> impl Responder {
+    let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+
+    let publish_at = match Validator::validate_publish_at(&body.publish_at) {
+        Ok(ts) => ts,
+        Err(e) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({"status": false, "message": e}));
+        }
+    };
+
+    let mut conn = pool.get().expect("DB connection error");
+    let post = match owned_post(&mut conn, post_id, &claims) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+    if post.status != POST_STATUS_DRAFT && post.status != POST_STATUS_SCHEDULED {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("A {} post cannot be scheduled", post.status)
+        }));
+    }
+
+    // Guard on status so a post published by the scheduler meanwhile is left alone
+    let updated = diesel::update(
+        live_posts()
+            .filter(posts::id.eq(post_id))
+            .filter(posts::status.eq_any([POST_STATUS_DRAFT, POST_STATUS_SCHEDULED])),
+    )
+    .set((
+        posts::status.eq(POST_STATUS_SCHEDULED),
+        posts::publish_at.eq(Some(publish_at)),
+    ))
+    .get_result::<Post>(&mut conn)
+    .optional();
+
+    match updated {
+        Ok(Some(post)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Post scheduled successfully",
+            "post": with_images(&mut conn, post)
+        })),
+        Ok(None) => HttpResponse::Conflict().json(serde_json::json!({
+            "status": false,
+            "message": format!("Post is already {}", POST_STATUS_PUBLISHED)
+        })),
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to schedule post",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
+
+/// Take a post off the queue; it goes back to being a draft.
+pub async fn cancel_scheduled_post(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+    if let Err(resp) = owned_post(&mut conn, post_id, &claims) {
+        return resp;
+    }
+
+    let updated = diesel::update(
+        live_posts()
+            .filter(posts::id.eq(post_id))
+            .filter(posts::status.eq(POST_STATUS_SCHEDULED)),
+    )
+    .set((
+        posts::status.eq(POST_STATUS_DRAFT),
+        posts::publish_at.eq(None::<NaiveDateTime>),
+    ))
+    .get_result::<Post>(&mut conn)
+    .optional();
+
+    match updated {
+        Ok(Some(post)) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Scheduled post cancelled",
+            "post": with_images(&mut conn, post)
+        })),
+        Ok(None) => HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "Post is not scheduled"
+        })),
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to cancel scheduled post",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use actix_web::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+
+use crate::db::Pool;
+use crate::utils::{
+    account_status::check_token_standing,
+    auth::{Claims, decode_claims},
+    stream_hub::{STREAM_MESSAGE, STREAM_NOTIFICATION, StreamEvent, subscribe},
+};
+
+const TOPICS: [&str; 3] = ["posts", "notifications", "messages"];
+
+/// How often an idle stream gets a comment line so proxies keep it open.
+const KEEPALIVE_SECS: u64 = 25;
+
+struct Subscription {
+    user_id: i64,
+    topics: Vec<String>,
+    events: Receiver<Arc<StreamEvent>>,
+    keepalive: actix_web::rt::time::Interval,
+    pool: web::Data<Pool>,
+    claims: Claims,
+    /// Set once the closing frame has been sent.
+    closed: bool,
+}
+
+impl Subscription {
+    /// Why the stream must end now: the token expired or the account lost
+    /// its standing since the stream was opened. The database check runs on
+    /// the blocking pool so open streams don't hold up the worker.
+    async fn revoked(&self) -> Option<&'static str> {
+        let now = chrono::Utc::now().timestamp();
+        if (self.claims.exp as i64) <= now {
+            return Some("Token expired");
+        }
+        let pool = self.pool.clone();
+        let claims = self.claims.clone();
+        let standing = web::block(move || {
+            let mut conn = pool.get()?;
+            Ok::<_, diesel::r2d2::PoolError>(check_token_standing(&mut conn, &claims).is_ok())
+        })
+        .await;
+        match standing {
+            Ok(Ok(true)) => None,
+            Ok(Ok(false)) => Some("Token is no longer valid"),
+            Ok(Err(e)) => {
+                eprintln!("DB connection error: {}", e);
+                None
+            }
+            Err(e) => {
+                eprintln!("Standing check failed: {}", e);
+                None
+            }
+        }
+    }
+
+    fn wants(&self, event: &StreamEvent) -> bool {
+        let topic = match event.event.as_str() {
+            STREAM_NOTIFICATION => "notifications",
+            STREAM_MESSAGE => "messages",
+            _ => "posts",
+        };
+        self.topics.iter().any(|t| t == topic) && event.audience.includes(self.user_id)
+    }
+}
+
+fn sse_frame(event: &str, data: &serde_json::Value) -> web::Bytes {
+    web::Bytes::from(format!("event: {}\ndata: {}\n\n", event, data))
+}
+
+/// Server-Sent Events for the caller: new, edited and deleted posts they can
+/// see, their notifications and messages in their conversations. `topics` narrows the stream (comma separated,
+/// default all). Browsers' EventSource cannot send headers, so the token may
+/// also come as `?token=`; query strings end up in proxy and server access
+/// logs, so prefer the header where the client can send it. The token and the
+/// account are checked again on every keepalive and the stream ends once
+/// either is no longer good.
+pub async fn stream_events(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| query.get("token").cloned());
+    let Some(token) = token else {
+        return HttpResponse::Unauthorized().json(serde_json::json!({
+            "status": false,
+            "message": "Token is required"
+        }));
+    };
+    let claims = match decode_claims(&token) {
+        Ok(c) => c,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": "Invalid token",
+                "error": e.to_string()
+            }));
+        }
+    };
+    {
+        let mut conn = pool.get().expect("DB connection error");
+        if let Err(resp) = check_token_standing(&mut conn, &claims) {
+            return resp;
+        }
+    }
+
+    let topics: Vec<String> = match query.get("topics") {
+        Some(list) => list
+            .split(',')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        None => TOPICS.iter().map(|t| t.to_string()).collect(),
+    };
+    if let Some(unknown) = topics.iter().find(|t| !TOPICS.contains(&t.as_str())) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": format!("Unknown topic {}; use {}", unknown, TOPICS.join(", "))
+        }));
+    }
+
+    let subscription = Subscription {
+        user_id: claims.id,
+        topics,
+        events: subscribe(),
+        keepalive: actix_web::rt::time::interval(Duration::from_secs(KEEPALIVE_SECS)),
+        pool,
+        claims,
+        closed: false,
+    };
+
+    let body = futures_util::stream::unfold(subscription, |mut sub| async move {
+        if sub.closed {
+            return None;
+        }
+        loop {
+            let frame = tokio::select! {
+                // The first tick fires right away and flushes the response headers
+                _ = sub.keepalive.tick() => match sub.revoked().await {
+                    Some(reason) => {
+                        sub.closed = true;
+                        sse_frame("closed", &serde_json::json!({ "reason": reason }))
+                    }
+                    None => web::Bytes::from_static(b": keepalive\n\n"),
+                },
+                received = sub.events.recv() => match received {
+                    Ok(event) if sub.wants(&event) => {
+                        sse_frame(&event.event, &serde_json::json!({
+                            "id": event.id,
+                            "data": event.data
+                        }))
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        sse_frame("lagged", &serde_json::json!({ "skipped": skipped }))
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+            return Some((Ok::<_, actix_web::Error>(frame), sub));
+        }
+    });
+
+    HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(body)
+}
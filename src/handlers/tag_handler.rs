@@ -0,0 +1,245 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::{Duration, Utc};
+use diesel::dsl::count_star;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    db::Pool,
+    handlers::post_handler::load_posts_with_users,
+    models::user::{POST_STATUS_PUBLISHED, PostWithUser},
+    schema::{mentions, post_tags, posts, tags, users},
+    utils::auth::require_claims,
+    utils::entities::{ENTITY_HASHTAG, ENTITY_MENTION, entity_names, parse_entities},
+    utils::notifications::DomainEvent,
+    utils::post_access::{listable_by, post_listing, viewable_by},
+    utils::soft_delete::{live_posts, live_users},
+};
+
+/// Rebuild the tags and mentions of a post from its description.
+/// Run inside the transaction that wrote the description. Returns the ids of
+/// users mentioned for the first time, so callers can let them know.
+pub fn sync_post_entities(
+    conn: &mut PgConnection,
+    post_id: i32,
+    description: &str,
+) -> QueryResult<Vec<i64>> {
+    let entities = parse_entities(description);
+    let tag_names = entity_names(&entities, ENTITY_HASHTAG);
+    let usernames = entity_names(&entities, ENTITY_MENTION);
+
+    if !tag_names.is_empty() {
+        let rows: Vec<_> = tag_names.iter().map(|n| tags::name.eq(n)).collect();
+        diesel::insert_into(tags::table)
+            .values(&rows)
+            .on_conflict(tags::name)
+            .do_nothing()
+            .execute(conn)?;
+    }
+    let tag_ids = tags::table
+        .filter(tags::name.eq_any(&tag_names))
+        .select(tags::id)
+        .load::<i32>(conn)?;
+
+    diesel::delete(post_tags::table.filter(post_tags::post_id.eq(post_id))).execute(conn)?;
+    if !tag_ids.is_empty() {
+        let rows: Vec<_> = tag_ids
+            .iter()
+            .map(|tag_id| (post_tags::post_id.eq(post_id), post_tags::tag_id.eq(tag_id)))
+            .collect();
+        diesel::insert_into(post_tags::table)
+            .values(&rows)
+            .execute(conn)?;
+    }
+
+    let mentioned = live_users()
+        .filter(users::username.eq_any(&usernames))
+        .select(users::id)
+        .load::<i64>(conn)?;
+    let already = mentions::table
+        .filter(mentions::post_id.eq(post_id))
+        .select(mentions::user_id)
+        .load::<i64>(conn)?;
+
+    diesel::delete(
+        mentions::table
+            .filter(mentions::post_id.eq(post_id))
+            .filter(mentions::user_id.ne_all(&mentioned)),
+    )
+    .execute(conn)?;
+
+    let new_mentions: Vec<i64> = mentioned
+        .into_iter()
+        .filter(|id| !already.contains(id))
+        .collect();
+    if !new_mentions.is_empty() {
+        let rows: Vec<_> = new_mentions
+            .iter()
+            .map(|user_id| (mentions::post_id.eq(post_id), mentions::user_id.eq(user_id)))
+            .collect();
+        diesel::insert_into(mentions::table)
+            .values(&rows)
+            .execute(conn)?;
+    }
+
+    Ok(new_mentions)
+}
+
+/// Notifications for mentioned users who can see the post; the rest hear
+/// about it once it is published to them.
+pub fn mention_events(
+    conn: &mut PgConnection,
+    post_id: i32,
+    actor_id: i64,
+    user_ids: &[i64],
+) -> QueryResult<Vec<DomainEvent>> {
+    let mut events = Vec::new();
+    for &user_id in user_ids {
+        let visible = post_listing()
+            .filter(viewable_by(user_id))
+            .filter(posts::status.eq(POST_STATUS_PUBLISHED))
+            .filter(posts::id.eq(post_id))
+            .count()
+            .get_result::<i64>(conn)?;
+        if visible > 0 {
+            events.push(DomainEvent::Mentioned {
+                actor_id,
+                user_id,
+                post_id,
+            });
+        }
+    }
+    Ok(events)
+}
+
+/// Everyone currently mentioned in a post.
+pub fn mentioned_users(conn: &mut PgConnection, post_id: i32) -> QueryResult<Vec<i64>> {
+    mentions::table
+        .filter(mentions::post_id.eq(post_id))
+        .select(mentions::user_id)
+        .load::<i64>(conn)
+}
+
+#[derive(Serialize)]
+struct TagPostsResponse {
+    status: bool,
+    tag: String,
+    posts: Vec<PostWithUser>,
+    total_post: i64,
+}
+
+pub async fn get_posts_by_tag(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let tag = path.into_inner().trim_start_matches('#').to_lowercase();
+
+    let page = query
+        .get("page")
+        .and_then(|p| p.parse::<i64>().ok())
+        .unwrap_or(1);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .unwrap_or(3);
+    let offset = (page - 1) * limit;
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let tagged = || {
+        post_tags::table
+            .inner_join(tags::table)
+            .filter(tags::name.eq(tag.clone()))
+            .select(post_tags::post_id)
+    };
+
+    let listing = post_listing()
+        .filter(listable_by(claims.id))
+        .filter(posts::id.eq_any(tagged()))
+        .order((posts::published_at.desc(), posts::id.desc()))
+        .limit(limit)
+        .offset(offset);
+    let posts_list = load_posts_with_users(&mut conn, listing).unwrap_or_default();
+
+    let total_count = post_listing()
+        .filter(listable_by(claims.id))
+        .filter(posts::id.eq_any(tagged()))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .unwrap_or(0);
+
+    HttpResponse::Ok().json(TagPostsResponse {
+        status: true,
+        tag,
+        posts: posts_list,
+        total_post: total_count,
+    })
+}
+
+#[derive(Serialize, Queryable)]
+struct TrendingTag {
+    name: String,
+    post_count: i64,
+}
+
+/// Tags used by the most public posts published in the last `hours` (default 24).
+pub async fn get_trending_tags(
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let hours = query
+        .get("hours")
+        .and_then(|h| h.parse::<i64>().ok())
+        .filter(|h| (1..=24 * 30).contains(h))
+        .unwrap_or(24);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse::<i64>().ok())
+        .filter(|l| (1..=100).contains(l))
+        .unwrap_or(10);
+    let since = Utc::now().naive_utc() - Duration::hours(hours);
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let public_posts = live_posts()
+        .inner_join(users::table)
+        .filter(users::deleted_at.is_null())
+        .filter(posts::status.eq(POST_STATUS_PUBLISHED))
+        .filter(posts::visibility.eq("public"))
+        .filter(posts::hidden_at.is_null())
+        .filter(posts::published_at.ge(since))
+        .select(posts::id);
+
+    let trending = post_tags::table
+        .inner_join(tags::table)
+        .filter(post_tags::post_id.eq_any(public_posts))
+        .group_by(tags::name)
+        .select((tags::name, count_star()))
+        .order((count_star().desc(), tags::name))
+        .limit(limit)
+        .load::<TrendingTag>(&mut conn);
+
+    match trending {
+        Ok(tags) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "hours": hours,
+            "tags": tags
+        })),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
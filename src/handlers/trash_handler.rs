@@ -0,0 +1,133 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::{
+    db::Pool,
+    handlers::post_image_handler::{load_post_images, to_post_data},
+    models::user::{POST_STATUS_PUBLISHED, Post},
+    schema::posts,
+    utils::auth::require_claims,
+    utils::soft_delete::{purge_at, restorable, retention},
+    utils::stream_hub::{STREAM_POST_CREATED, emit_post},
+};
+
+/// The caller's trashed posts that can still be restored, most recently deleted first.
+pub async fn list_trashed_posts(req: HttpRequest, pool: web::Data<Pool>) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let cutoff = Utc::now().naive_utc() - retention();
+    let trashed = posts::table
+        .filter(posts::userid.eq(claims.id))
+        .filter(posts::deleted_at.gt(cutoff))
+        .order((posts::deleted_at.desc(), posts::id.desc()))
+        .load::<Post>(&mut conn);
+
+    let trashed = match trashed {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    };
+
+    let ids: Vec<i32> = trashed.iter().map(|p| p.id).collect();
+    let mut images = load_post_images(&mut conn, &ids).unwrap_or_default();
+    let posts: Vec<serde_json::Value> = trashed
+        .into_iter()
+        .map(|p| {
+            let deleted_at = p.deleted_at;
+            let imgs = images.remove(&p.id).unwrap_or_default();
+            serde_json::json!({
+                "post": to_post_data(p, imgs),
+                "deleted_at": deleted_at,
+                "restore_until": deleted_at.map(purge_at)
+            })
+        })
+        .collect();
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": true,
+        "posts": posts
+    }))
+}
+
+pub async fn restore_post(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let post_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    let mut conn = pool.get().expect("DB connection error");
+
+    let trashed = posts::table
+        .filter(posts::id.eq(post_id))
+        .filter(posts::userid.eq(claims.id))
+        .filter(posts::deleted_at.is_not_null())
+        .first::<Post>(&mut conn)
+        .optional();
+
+    match trashed {
+        Ok(Some(p)) if p.deleted_at.is_some_and(restorable) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Gone().json(serde_json::json!({
+                "status": false,
+                "message": "The restore window for this post has passed"
+            }));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": "Post not found in trash"
+            }));
+        }
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            }));
+        }
+    }
+
+    let restored = diesel::update(posts::table.filter(posts::id.eq(post_id)))
+        .set(posts::deleted_at.eq(None::<NaiveDateTime>))
+        .get_result::<Post>(&mut conn);
+
+    match restored {
+        Ok(post) => {
+            if post.status == POST_STATUS_PUBLISHED {
+                emit_post(&mut conn, STREAM_POST_CREATED, post.id);
+            }
+            let images = load_post_images(&mut conn, &[post.id])
+                .map(|mut m| m.remove(&post.id).unwrap_or_default())
+                .unwrap_or_default();
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Post restored successfully",
+                "post": to_post_data(post, images)
+            }))
+        }
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to restore post",
+                "error": e.to_string()
+            }))
+        }
+    }
+}
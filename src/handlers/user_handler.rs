@@ -1,99 +1,72 @@
-use actix_multipart::Multipart;
-use actix_web::{HttpResponse, Responder, web};
-use bcrypt::{DEFAULT_COST, hash, verify};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
-use futures_util::TryStreamExt as _;
+use diesel::sql_types::BigInt;
 use jsonwebtoken::{EncodingKey, Header, encode};
 use serde::{Serialize};
 use std::collections::HashMap;
 use std::env;
+use validator::{Validate, ValidationError};
 
 use crate::{
     db::Pool,
-    models::user::{NewUser, User,LoginRequest,Claims,ChangePasswordForm,UserData},
-    schema::users,
-    utils::{file_upload::save_profile_image, validation::Validator},
+    models::user::{NewUser, User,LoginRequest,Claims,ChangePasswordForm,UserData,DeleteAccountRequest,USER_STATUS_ACTIVE,RegisterForm,ProfileEdit,
+        AUDIT_LOGIN,AUDIT_LOGIN_FAILED,AUDIT_PASSWORD_CHANGED,AUDIT_PROFILE_UPDATED,AUDIT_TARGET_USER},
+    utils::auth::require_claims,
+    schema::{uploads, users},
+    utils::{
+        file_upload::{PROFILE_DIR, save_profile_image},
+        multipart_form::MultipartForm,
+        upload::remove_stored_files,
+        soft_delete::{hard_delete, live_users, purge_at, restorable},
+        blocks::users_visible_to,
+        account_status::{effective_status, refusal},
+        audit,
+        password::{hash_new_password, hash_password, needs_rehash, set_password, verify_password},
+        validation::{Validator, add_password_issues, validation_response},
+    },
 };
 
 
 
-pub async fn register_user(pool: web::Data<Pool>, mut payload: Multipart) -> impl Responder {
-    let mut profile_filename: Option<String> = None;
-    let mut email_field = String::new();
-    let mut firstname_field = String::new();
-    let mut lastname_field = String::new();
-    let mut phone_field = String::new();
-    let mut password_field = String::new();
-    let mut image_bytes: Option<Vec<u8>> = None;
+pub async fn register_user(pool: web::Data<Pool>, payload: MultipartForm<RegisterForm>) -> impl Responder {
+    let MultipartForm { fields: mut form, mut files } = payload;
+    form.username = form
+        .username
+        .map(|u| u.trim().to_lowercase())
+        .filter(|u| !u.is_empty());
+    let profile_upload = files.take_one("profile");
 
-    while let Ok(Some(mut field)) = payload.try_next().await {
-        let name = field.name().to_string();
-
-        if name == "profile" {
-            let content_disposition = field.content_disposition();
-            let filename = content_disposition
-                .get_filename()
-                .unwrap_or("unknown.jpg")
-                .to_string();
-
-            if let Err(e) = Validator::validate_image_type(&filename) {
-                return HttpResponse::BadRequest()
-                    .json(serde_json::json!({ "status": false, "message": e }));
-            }
-
-            let mut bytes = Vec::new();
-            while let Ok(Some(chunk)) = field.try_next().await {
-                bytes.extend_from_slice(&chunk);
-            }
-
-            if bytes.len() > 3 * 1024 * 1024 {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "status": false,
-                    "message": "File size should be less than 3MB"
-                }));
-            }
-
-            image_bytes = Some(bytes);
-            profile_filename = Some(filename);
-        } else {
-            let mut data: Vec<u8> = Vec::new();
-            while let Ok(Some(chunk)) = field.try_next().await {
-                data.extend_from_slice(&chunk);
-            }
-            let value = String::from_utf8_lossy(&data).to_string();
-            match name.as_str() {
-                "email" => email_field = value,
-                "firstname" => firstname_field = value,
-                "lastname" => lastname_field = value,
-                "ph" => phone_field = value,
-                "password" => password_field = value,
-                _ => {}
-            }
-        }
+    let mut errors = form.validate().err().unwrap_or_default();
+    let user_inputs = [form.email.as_str(), form.firstname.as_str(), form.lastname.as_str(), form.username.as_deref().unwrap_or_default()];
+    if let Err(issues) = Validator::validate_password(&form.password, &user_inputs) {
+        add_password_issues(&mut errors, "password", issues);
     }
-
-    if email_field.is_empty()
-        || firstname_field.is_empty()
-        || lastname_field.is_empty()
-        || phone_field.is_empty()
-        || password_field.is_empty()
-    {
-        return HttpResponse::BadRequest()
-            .json(serde_json::json!({"status": false, "message": "All fields are required"}));
+    if profile_upload.is_none() {
+        errors.add("profile", ValidationError::new("required").with_message("Profile image is required".into()));
     }
+    let profile_upload = match profile_upload {
+        Some(upload) if errors.is_empty() => upload,
+        _ => return validation_response(&errors),
+    };
 
-    let (image_data, filename) = match (image_bytes, profile_filename) {
-        (Some(bytes), Some(name)) => (bytes, name),
-        _ => {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "status": false,
-                "message": "Profile image is required"
-            }));
-        }
+    let hashed_pwd = match hash_new_password(&form.password, &user_inputs) {
+        Ok(h) => h,
+        Err(e) => return e.to_response(),
     };
 
+    // Names and phone numbers are stored in their normalized form; all passed validation above
+    let email_field = form.email;
+    let firstname_field = Validator::normalize_firstname(&form.firstname).unwrap_or_default();
+    let lastname_field = Validator::normalize_lastname(&form.lastname).unwrap_or_default();
+    let phone_field = Validator::normalize_phone(&form.ph).unwrap_or_default();
+    let username_field = form.username.unwrap_or_default();
+
     let mut conn = pool.get().expect("DB connection error");
 
+    // Trashed accounts keep their email until purged
     let exists = users::table
         .filter(users::email.eq(&email_field))
         .first::<User>(&mut conn)
@@ -107,8 +80,23 @@ pub async fn register_user(pool: web::Data<Pool>, mut payload: Multipart) -> imp
         }));
     }
 
-    let saved_filename = match save_profile_image(image_data, &filename) {
-        Ok(name) => name,
+    if !username_field.is_empty() {
+        let taken = users::table
+            .filter(users::username.eq(&username_field))
+            .select(users::id)
+            .first::<i64>(&mut conn)
+            .optional()
+            .expect("DB query failed");
+        if taken.is_some() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": "Username already taken"
+            }));
+        }
+    }
+
+    let saved_image = match save_profile_image(profile_upload) {
+        Ok(stored) => stored,
         Err(e) => {
             eprintln!("Failed to save profile image: {}", e);
             return HttpResponse::InternalServerError()
@@ -116,35 +104,33 @@ pub async fn register_user(pool: web::Data<Pool>, mut payload: Multipart) -> imp
         }
     };
 
-    let hashed_pwd = match hash(&password_field, DEFAULT_COST) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("Failed to hash password: {}", e);
-            return HttpResponse::InternalServerError().json(
-                serde_json::json!({"status": false, "message": "Failed to process password"}),
-            );
-        }
-    };
-
     let new_user = NewUser {
-        profile: saved_filename,
+        profile: saved_image.storage_key.clone(),
         email: email_field,
         firstname: firstname_field,
         lastname: lastname_field,
         ph: phone_field,
         password: hashed_pwd,
+        username: Some(username_field).filter(|u| !u.is_empty()),
     };
 
-    match diesel::insert_into(users::table)
-        .values(&new_user)
-        .execute(&mut conn)
-    {
+    let inserted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(uploads::table)
+            .values(&saved_image)
+            .execute(conn)?;
+        diesel::insert_into(users::table)
+            .values(&new_user)
+            .execute(conn)
+    });
+
+    match inserted {
         Ok(_) => HttpResponse::Created().json(serde_json::json!({
             "status": true,
             "message": "User created successfully"
         })),
         Err(e) => {
             eprintln!("Database insert failed: {}", e);
+            remove_stored_files(PROFILE_DIR, &[saved_image.storage_key]);
             HttpResponse::InternalServerError().json(serde_json::json!({
                 "status": false,
                 "message": "Failed to create user",
@@ -159,7 +145,16 @@ pub async fn register_user(pool: web::Data<Pool>, mut payload: Multipart) -> imp
 
 
 
-pub async fn login_user(pool: web::Data<Pool>, body: web::Json<LoginRequest>) -> impl Responder {
+fn audit_failed_login(conn: &mut PgConnection, req: &HttpRequest, user_id: Option<i64>, email: &str, reason: &str) {
+    let mut entry = audit::event(req, None, AUDIT_LOGIN_FAILED, user_id.map(|id| (AUDIT_TARGET_USER, id)));
+    entry.after = Some(serde_json::json!({ "email": email, "reason": reason }));
+    audit::record(conn, entry);
+}
+
+pub async fn login_user(req: HttpRequest, pool: web::Data<Pool>, body: web::Json<LoginRequest>) -> impl Responder {
+    if let Err(errors) = body.validate() {
+        return validation_response(&errors);
+    }
     let mut conn = pool.get().expect("DB connection error");
     let email_field = body.email.trim().to_string();
     let password_field = body.password.trim().to_string();
@@ -173,6 +168,7 @@ pub async fn login_user(pool: web::Data<Pool>, body: web::Json<LoginRequest>) ->
     let user = match user {
         Some(u) => u,
         None => {
+            audit_failed_login(&mut conn, &req, None, &email_field, "unknown email");
             return HttpResponse::BadRequest().json(serde_json::json!({
                 "status": false,
                 "message": "User not found"
@@ -180,15 +176,47 @@ pub async fn login_user(pool: web::Data<Pool>, body: web::Json<LoginRequest>) ->
         }
     };
 
-    if !verify(&password_field, &user.password).unwrap_or(false) {
+    if !verify_password(&password_field, &user.password) {
+        audit_failed_login(&mut conn, &req, Some(user.id), &email_field, "incorrect password");
         return HttpResponse::BadRequest().json(serde_json::json!({
             "status": false,
             "message": "Incorrect password"
         }));
     }
 
+    // Only someone who knows the password learns the account is in the trash
+    if user.deleted_at.is_some() {
+        audit_failed_login(&mut conn, &req, Some(user.id), &email_field, "account deleted");
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "status": false,
+            "message": "This account has been deleted; restore it through /api/restoreAccount"
+        }));
+    }
+
+    let status = effective_status(&user.status, user.suspended_until);
+    if status != USER_STATUS_ACTIVE {
+        audit_failed_login(&mut conn, &req, Some(user.id), &email_field, &format!("account {}", status));
+        return refusal(status, user.suspended_until, user.suspension_reason.as_deref());
+    }
+
+    // Move hashes from older algorithms or settings over while the plain password is at hand
+    if needs_rehash(&user.password) {
+        match hash_password(&password_field) {
+            Ok(rehashed) => {
+                if let Err(e) = diesel::update(users::table.filter(users::id.eq(user.id)))
+                    .set(users::password.eq(rehashed))
+                    .execute(&mut conn)
+                {
+                    eprintln!("Failed to store rehashed password: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to rehash password: {}", e),
+        }
+    }
+
     let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "supersecretkey".into());
-    let exp = chrono::Utc::now()
+    let now = chrono::Utc::now();
+    let exp = now
         .checked_add_signed(chrono::Duration::days(1))
         .unwrap()
         .timestamp() as usize;
@@ -199,6 +227,7 @@ pub async fn login_user(pool: web::Data<Pool>, body: web::Json<LoginRequest>) ->
         firstname: user.firstname.clone(),
         lastname: user.lastname.clone(),
         exp,
+        iat: now.timestamp() as usize,
     };
 
     let token = encode(
@@ -208,6 +237,8 @@ pub async fn login_user(pool: web::Data<Pool>, body: web::Json<LoginRequest>) ->
     )
     .unwrap();
 
+    audit::record(&mut conn, audit::event(&req, Some(user.id), AUDIT_LOGIN, Some((AUDIT_TARGET_USER, user.id))));
+
     HttpResponse::Ok().json(serde_json::json!({
         "status": true,
         "message": "Login successful",
@@ -215,17 +246,61 @@ pub async fn login_user(pool: web::Data<Pool>, body: web::Json<LoginRequest>) ->
     }))
 }
 
+/// Profile fields compared by the audit log when a user is edited.
+fn audited_profile(user: &User) -> serde_json::Value {
+    serde_json::json!({
+        "firstname": user.firstname,
+        "lastname": user.lastname,
+        "email": user.email,
+        "ph": user.ph,
+        "username": user.username,
+        "profile": user.profile
+    })
+}
+
+pub type UserDataColumns = (
+    users::id,
+    users::firstname,
+    users::lastname,
+    users::email,
+    users::ph,
+    users::profile,
+    users::username,
+    SqlLiteral<BigInt>,
+    SqlLiteral<BigInt>,
+);
+
+/// Columns for `UserData`, including follower and following counts of live users.
+pub fn user_data_columns() -> UserDataColumns {
+    (
+        users::id,
+        users::firstname,
+        users::lastname,
+        users::email,
+        users::ph,
+        users::profile,
+        users::username,
+        sql::<BigInt>("(SELECT COUNT(*) FROM follows JOIN users u ON u.id = follows.follower_id AND u.deleted_at IS NULL WHERE follows.followee_id = users.id)"),
+        sql::<BigInt>("(SELECT COUNT(*) FROM follows JOIN users u ON u.id = follows.followee_id AND u.deleted_at IS NULL WHERE follows.follower_id = users.id)"),
+    )
+}
+
 #[derive(Serialize)]
 pub struct UsersResponse {
-    status: bool,
-    total_users: i64,
-    users: Vec<UserData>,
+    pub status: bool,
+    pub total_users: i64,
+    pub users: Vec<UserData>,
 }
 
 pub async fn get_all_users(
+    req: HttpRequest,
     pool: web::Data<Pool>,
     query: web::Query<HashMap<String, String>>,
 ) -> impl Responder {
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
     let page = query
         .get("page")
         .and_then(|p| p.parse::<i64>().ok())
@@ -238,17 +313,10 @@ pub async fn get_all_users(
 
     let mut conn = pool.get().expect("DB connection error");
 
-    let total_users: i64 = users::table.count().get_result(&mut conn).unwrap_or(0);
+    let total_users: i64 = users_visible_to(claims.id).count().get_result(&mut conn).unwrap_or(0);
 
-    let user_list = users::table
-        .select((
-            users::id,
-            users::firstname,
-            users::lastname,
-            users::email,
-            users::ph,
-            users::profile,
-        ))
+    let user_list = users_visible_to(claims.id)
+        .select(user_data_columns())
         .limit(limit)
         .offset(offset)
         .load::<UserData>(&mut conn)
@@ -256,24 +324,21 @@ pub async fn get_all_users(
 
     HttpResponse::Ok().json(UsersResponse {
         status: true,
-        total_users: total_users,
+        total_users,
         users: user_list,
     })
 }
 
-pub async fn get_user_by_id(pool: web::Data<Pool>, path: web::Path<i64>) -> impl Responder {
+pub async fn get_user_by_id(req: HttpRequest, pool: web::Data<Pool>, path: web::Path<i64>) -> impl Responder {
     let user_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
     let mut conn = pool.get().expect("DB connection error");
 
-    let user_result = users::table
-        .select((
-            users::id,
-            users::firstname,
-            users::lastname,
-            users::email,
-            users::ph,
-            users::profile,
-        ))
+    let user_result = users_visible_to(claims.id)
+        .select(user_data_columns())
         .filter(users::id.eq(user_id))
         .first::<UserData>(&mut conn)
         .optional();
@@ -295,16 +360,27 @@ pub async fn get_user_by_id(pool: web::Data<Pool>, path: web::Path<i64>) -> impl
 }
 
 pub async fn update_user(
+    req: HttpRequest,
     pool: web::Data<Pool>,
     path: web::Path<i64>,
-    mut payload: Multipart,
+    payload: MultipartForm<ProfileEdit>,
 ) -> impl Responder {
     use crate::schema::users::dsl::*;
 
     let user_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    if claims.id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "status": false,
+            "message": "You can only update your own profile"
+        }));
+    }
     let mut conn = pool.get().expect("DB connection error");
 
-    let existing_user = users
+    let existing_user = live_users()
         .filter(id.eq(user_id))
         .first::<User>(&mut conn)
         .optional()
@@ -317,65 +393,41 @@ pub async fn update_user(
         }));
     }
     let mut user = existing_user.unwrap();
-    let mut image_bytes: Option<Vec<u8>> = None;
-    let mut uploaded_filename: Option<String> = None;
-
-    while let Ok(Some(mut field)) = payload.try_next().await {
-        let name = field.name().to_string();
-        if name == "profile" {
-            let cd = field.content_disposition();
-            let filename = cd.get_filename().unwrap_or("unknown.jpg").to_string();
-
-            if let Err(e) = Validator::validate_image_type(&filename) {
-                return HttpResponse::BadRequest()
-                    .json(serde_json::json!({ "status": false, "message": e }));
-            }
-            let mut bytes = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
-                bytes.extend_from_slice(&chunk);
-            }
-
-            if bytes.len() > 3 * 1024 * 1024 {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "status": false,
-                    "message": "File size should be less than 3MB"
-                }));
-            }
-
-            image_bytes = Some(bytes);
-            uploaded_filename = Some(filename);
-        } else {
-            let mut data = Vec::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
-                data.extend_from_slice(&chunk);
-            }
-            let value = String::from_utf8_lossy(&data)
-                .to_string()
-                .trim()
-                .to_string();
-            match name.as_str() {
-                "firstname" => user.firstname = value,
-                "lastname" => user.lastname = value,
-                "email" => user.email = value,
-                "ph" => user.ph = value,
-                "password" => user.password = value,
-                _ => {}
-            }
-        }
+    let before = audited_profile(&user);
+    let MultipartForm { fields: mut edit, mut files } = payload;
+    if edit.password.is_some() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "Passwords can't be changed here; use /api/changePassword"
+        }));
+    }
+    let trimmed = |value: Option<String>| value.map(|v| v.trim().to_string());
+    edit.email = trimmed(edit.email);
+    edit.firstname = trimmed(edit.firstname);
+    edit.lastname = trimmed(edit.lastname);
+    edit.ph = trimmed(edit.ph);
+    edit.username = trimmed(edit.username).map(|u| u.to_lowercase()).filter(|u| !u.is_empty());
+    if let Err(errors) = edit.validate() {
+        return validation_response(&errors);
     }
 
-    if let Err(e) = Validator::validate_firstname(&user.firstname) {
-        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
+    // Stored in normalized form; all passed validation above
+    if let Some(value) = edit.email {
+        user.email = value;
+    }
+    if let Some(value) = edit.firstname {
+        user.firstname = Validator::normalize_firstname(&value).unwrap_or_default();
     }
-    if let Err(e) = Validator::validate_lastname(&user.lastname) {
-        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
+    if let Some(value) = edit.lastname {
+        user.lastname = Validator::normalize_lastname(&value).unwrap_or_default();
     }
-    if let Err(e) = Validator::validate_phone(&user.ph) {
-        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
+    if let Some(value) = edit.ph {
+        user.ph = Validator::normalize_phone(&value).unwrap_or_default();
     }
-    if let Err(e) = Validator::validate_email(&user.email) {
-        return HttpResponse::BadRequest().json(serde_json::json!({"status": false, "message": e}));
+    if edit.username.is_some() {
+        user.username = edit.username;
     }
+    let profile_upload = files.take_one("profile");
 
     let email_exists = users
         .filter(email.eq(&user.email))
@@ -391,83 +443,124 @@ pub async fn update_user(
         }));
     }
 
-    // Handle the profile image update
-    if let Some(bytes) = image_bytes {
-        let saved_name = match save_profile_image(bytes, &uploaded_filename.unwrap()) {
-            Ok(n) => n,
+    if let Some(name) = &user.username {
+        let username_taken = users
+            .filter(username.eq(name))
+            .filter(id.ne(user_id))
+            .select(id)
+            .first::<i64>(&mut conn)
+            .optional()
+            .expect("DB check failed");
+        if username_taken.is_some() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": "Username already taken"
+            }));
+        }
+    }
+
+    // Store the new profile image; the old one is only removed once the update commits
+    let old_profile = user.profile.clone();
+    let saved_image = match profile_upload {
+        Some(upload) => match save_profile_image(upload) {
+            Ok(stored) => Some(stored),
             Err(e) => {
                 return HttpResponse::InternalServerError()
                     .json(serde_json::json!({"status": false, "message": e}));
             }
-        };
-
-        // Delete old profile file if exists
-        let old_profile_path = format!("files/usersProfiles/{}", user.profile);
-        if std::fs::metadata(&old_profile_path).is_ok() {
-            let _ = std::fs::remove_file(&old_profile_path);
-        }
-
-        user.profile = saved_name;
-    }
-
-    // Hash password if changed
-    if Validator::validate_password(&user.password).is_ok() {
-        user.password = hash(&user.password, DEFAULT_COST).unwrap();
+        },
+        None => None,
+    };
+    if let Some(stored) = &saved_image {
+        user.profile = stored.storage_key.clone();
     }
 
     // Perform update
-    let updated_rows = diesel::update(users.filter(id.eq(user_id)))
-        .set((
-            firstname.eq(&user.firstname),
-            lastname.eq(&user.lastname),
-            email.eq(&user.email),
-            ph.eq(&user.ph),
-            password.eq(&user.password),
-            profile.eq(&user.profile),
-        ))
-        .execute(&mut conn)
-        .expect("Failed to update user");
+    let updated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        if let Some(stored) = &saved_image {
+            diesel::insert_into(uploads::table)
+                .values(stored)
+                .execute(conn)?;
+            // The old image's file is removed below once this commits
+            diesel::delete(uploads::table.filter(uploads::storage_key.eq(&old_profile)))
+                .execute(conn)?;
+        }
+        diesel::update(users.filter(id.eq(user_id)))
+            .set((
+                firstname.eq(&user.firstname),
+                lastname.eq(&user.lastname),
+                email.eq(&user.email),
+                ph.eq(&user.ph),
+                profile.eq(&user.profile),
+                username.eq(&user.username),
+            ))
+            .execute(conn)
+    });
+
+    let new_files: Vec<String> = saved_image.into_iter().map(|s| s.storage_key).collect();
 
-    if updated_rows == 0 {
-        return HttpResponse::NotFound()
-            .json(serde_json::json!({ "status": false, "message": "User not found" }));
+    match updated {
+        Ok(0) => {
+            remove_stored_files(PROFILE_DIR, &new_files);
+            HttpResponse::NotFound()
+                .json(serde_json::json!({ "status": false, "message": "User not found" }))
+        }
+        Ok(_) => {
+            // Delete old profile file now that nothing references it
+            if !new_files.is_empty() {
+                remove_stored_files(PROFILE_DIR, &[old_profile]);
+            }
+            let (old, new) = audit::changes(before, audited_profile(&user));
+            if new.is_some() {
+                let mut entry = audit::event(&req, Some(claims.id), AUDIT_PROFILE_UPDATED, Some((AUDIT_TARGET_USER, user_id)));
+                entry.before = old;
+                entry.after = new;
+                audit::record(&mut conn, entry);
+            }
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "User updated successfully"
+            }))
+        }
+        Err(e) => {
+            eprintln!("Database update failed: {}", e);
+            remove_stored_files(PROFILE_DIR, &new_files);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to update user",
+                "error": e.to_string()
+            }))
+        }
     }
-
-    HttpResponse::Ok().json(serde_json::json!({
-        "status": true,
-        "message": "User updated successfully"
-    }))
 }
 
 pub async fn change_password(
+    req: HttpRequest,
     pool: web::Data<Pool>,
     path: web::Path<i64>,
     form: web::Json<ChangePasswordForm>,
 ) -> impl Responder {
     let user_id = path.into_inner();
-    let old_password = &form.old_password;
-    let new_password = &form.new_password;
-
-    // Validate presence
-    if old_password.is_empty() || new_password.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    if claims.id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
             "status": false,
-            "message": "All fields required"
+            "message": "You can only change your own password"
         }));
     }
-
-    // Validate new_password with your Regex validation in Validator
-    if let Err(e) = Validator::validate_password(new_password) {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "status": false,
-            "message": e,
-        }));
+    if let Err(errors) = form.validate() {
+        return validation_response(&errors);
     }
+    let old_password = &form.old_password;
+    let new_password = &form.new_password;
 
     let mut conn = pool.get().expect("DB connection error");
 
     // Fetch hashed password from DB
-    let result = users::table
+    let result = live_users()
         .filter(users::id.eq(user_id))
         .select(users::password)
         .first::<String>(&mut conn)
@@ -490,7 +583,7 @@ pub async fn change_password(
     };
 
     // Check old password matches
-    if !verify(old_password, &hashed_password).unwrap_or(false) {
+    if !verify_password(old_password, &hashed_password) {
         return HttpResponse::BadRequest().json(serde_json::json!({
             "status": false,
             "message": "Old password is incorrect"
@@ -498,37 +591,177 @@ pub async fn change_password(
     }
 
     // New password should not be same as old password
-    if verify(new_password, &hashed_password).unwrap_or(false) {
+    if verify_password(new_password, &hashed_password) {
         return HttpResponse::BadRequest().json(serde_json::json!({
             "status": false,
             "message": "New password cannot be same as old password"
         }));
     }
 
-    // Hash new password
-    let new_hashed = match hash(new_password, DEFAULT_COST) {
-        Ok(h) => h,
+    match set_password(&mut conn, user_id, new_password).map_err(|e| e.for_field("new_password")) {
+        Ok(()) => {
+            audit::record(&mut conn, audit::event(&req, Some(claims.id), AUDIT_PASSWORD_CHANGED, Some((AUDIT_TARGET_USER, user_id))));
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Password changed successfully; please log in again"
+            }))
+        }
+        Err(e) => e.to_response(),
+    }
+}
+
+/// Delete the caller's own account after re-checking their password.
+/// By default the account goes to the trash and is purged with its posts and
+/// files once the retention window has passed; `permanent` erases it right away.
+pub async fn delete_user(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    path: web::Path<i64>,
+    body: web::Json<DeleteAccountRequest>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let claims = match require_claims(&req) {
+        Ok(c) => c,
+        Err(resp) => return resp,
+    };
+    if claims.id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "status": false,
+            "message": "You can only delete your own account"
+        }));
+    }
+
+    let mut conn = pool.get().expect("DB connection error");
+
+    let stored_password = live_users()
+        .filter(users::id.eq(user_id))
+        .select(users::password)
+        .first::<String>(&mut conn)
+        .optional();
+
+    match stored_password {
+        Ok(Some(hashed)) if verify_password(body.password.trim(), &hashed) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "status": false,
+                "message": "Incorrect password"
+            }));
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": "User not found"
+            }));
+        }
         Err(e) => {
             return HttpResponse::InternalServerError().json(serde_json::json!({
                 "status": false,
-                "message": format!("Hashing error: {}", e)
+                "message": e.to_string()
             }));
         }
-    };
+    }
+
+    if body.permanent {
+        // Posts cascade with the user row; hard_delete also clears their files
+        return match hard_delete(&mut conn, &[user_id], &[]) {
+            Ok(report) => HttpResponse::Ok().json(serde_json::json!({
+                "status": true,
+                "message": "Account permanently deleted",
+                "deleted_posts": report.posts,
+                "deleted_files": report.files
+            })),
+            Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Failed to delete account",
+                "error": e.to_string()
+            })),
+        };
+    }
 
-    // Update password in DB
-    let updated = diesel::update(users::table.filter(users::id.eq(user_id)))
-        .set(users::password.eq(new_hashed))
+    let deleted_at = chrono::Utc::now().naive_utc();
+    let updated = diesel::update(live_users().filter(users::id.eq(user_id)))
+        // Tokens issued before the deletion stay revoked after a restore
+        .set((
+            users::deleted_at.eq(Some(deleted_at)),
+            users::status_changed_at.eq(Some(deleted_at)),
+        ))
         .execute(&mut conn);
 
     match updated {
+        Ok(0) => HttpResponse::NotFound().json(serde_json::json!({
+            "status": false,
+            "message": "User not found"
+        })),
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
+            "status": true,
+            "message": "Account deleted",
+            "restore_until": purge_at(deleted_at)
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "status": false,
+            "message": "Failed to delete account",
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// Bring back a deleted account within the retention window, using its credentials.
+pub async fn restore_account(
+    pool: web::Data<Pool>,
+    body: web::Json<LoginRequest>,
+) -> impl Responder {
+    let mut conn = pool.get().expect("DB connection error");
+    let email_field = body.email.trim().to_string();
+    let password_field = body.password.trim().to_string();
+
+    let user = users::table
+        .filter(users::email.eq(&email_field))
+        .filter(users::deleted_at.is_not_null())
+        .first::<User>(&mut conn)
+        .optional();
+
+    let user = match user {
+        Ok(Some(u)) => u,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": false,
+                "message": "No deleted account found for this email"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": e.to_string()
+            }));
+        }
+    };
+
+    if !verify_password(&password_field, &user.password) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "status": false,
+            "message": "Incorrect password"
+        }));
+    }
+
+    if !user.deleted_at.is_some_and(restorable) {
+        return HttpResponse::Gone().json(serde_json::json!({
+            "status": false,
+            "message": "The restore window for this account has passed"
+        }));
+    }
+
+    let restored = diesel::update(users::table.filter(users::id.eq(user.id)))
+        .set(users::deleted_at.eq(None::<chrono::NaiveDateTime>))
+        .execute(&mut conn);
+
+    match restored {
         Ok(_) => HttpResponse::Ok().json(serde_json::json!({
             "status": true,
-            "message": "Password changed successfully"
+            "message": "Account restored; you can log in again"
         })),
         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
             "status": false,
-            "message": "Failed to update password",
+            "message": "Failed to restore account",
             "error": e.to_string()
         })),
     }
@@ -10,12 +10,24 @@ use actix_files as fs;
 use actix_web::{App, HttpServer, web};
 use db::init_pool;
 use routes::routes::user_routes;
+use utils::upload::MultipartLimit;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Initialize the database pool
     let pool = init_pool();
 
+    // Admin commands: `rust_api gc-media [--dry-run] [--grace-secs N]`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("gc-media") {
+        return utils::media_gc::run_cli(&pool, &args[1..]).map_err(std::io::Error::other);
+    }
+
+    utils::media_gc::spawn_background(pool.clone());
+    utils::post_scheduler::spawn_background(pool.clone());
+    utils::soft_delete::spawn_background(pool.clone());
+    utils::stream_hub::spawn_listener();
+
     println!("Server running at http://127.0.0.1:8000");
 
     HttpServer::new(move || {
@@ -31,8 +43,8 @@ async fn main() -> std::io::Result<()> {
                     .max_age(3600),
             )
             .app_data(web::Data::new(pool.clone()))
-            .service(fs::Files::new("/profile", "./files/usersProfiles").show_files_listing())
-            .service(fs::Files::new("/post", "./files/userPost").show_files_listing())
+            .app_data(web::Data::new(MultipartLimit::default()))
+            .service(fs::Files::new("/profile", "./files/usersProfiles"))
             .service(web::scope("/api").configure(user_routes))
     })
     .bind(("127.0.0.1", 8000))?
@@ -1,8 +1,15 @@
 use chrono::NaiveDateTime;
 use diesel::{Queryable, Insertable};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
-use crate::schema::{posts, users};
+use crate::utils::entities::Entity;
+use crate::utils::multipart_form::{FileLimits, MultipartFields, one_or_many};
+use crate::schema::{
+    audit_events, blocks, conversation_members, follows, messages, moderation_actions, mutes,
+    notification_preferences, notifications, post_images, post_revisions, posts, reports, uploads,
+    users,
+};
 
 // USER MODELS 
 
@@ -17,6 +24,14 @@ pub struct User {
     pub password: String,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub username: Option<String>,
+    pub role: String,
+    pub suspended_until: Option<NaiveDateTime>,
+    pub suspension_reason: Option<String>,
+    pub status: String,
+    pub status_changed_at: Option<NaiveDateTime>,
+    pub password_changed_at: Option<NaiveDateTime>,
 }
 
 #[derive(Insertable, Deserialize)]
@@ -28,12 +43,59 @@ pub struct NewUser {
     pub lastname: String,
     pub ph: String,
     pub password: String,
+    pub username: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct LoginRequest {
+    #[validate(length(min = 1, code = "required", message = "Email is required"))]
+    pub email: String,
+    #[validate(length(min = 1, code = "required", message = "Password is required"))]
+    pub password: String,
+}
+
+/// Text fields of a registration; the password policy is applied on top
+/// since it needs the other fields.
+#[derive(Deserialize, Default, Validate)]
+#[serde(default)]
+pub struct RegisterForm {
+    #[validate(custom(function = "crate::utils::validation::check_email"))]
     pub email: String,
+    #[validate(custom(function = "crate::utils::validation::check_firstname"))]
+    pub firstname: String,
+    #[validate(custom(function = "crate::utils::validation::check_lastname"))]
+    pub lastname: String,
+    #[validate(custom(function = "crate::utils::validation::check_phone"))]
+    pub ph: String,
     pub password: String,
+    #[validate(custom(function = "crate::utils::validation::check_username"))]
+    pub username: Option<String>,
+}
+
+impl MultipartFields for RegisterForm {
+    const FILES: &'static [FileLimits] = &[FileLimits::images("profile", 1)];
+}
+
+/// Fields sent to edit a profile; absent ones are left alone and not
+/// re-checked, so older stored values don't block unrelated edits.
+/// `password` is only read to refuse it.
+#[derive(Deserialize, Validate)]
+pub struct ProfileEdit {
+    #[validate(custom(function = "crate::utils::validation::check_email"))]
+    pub email: Option<String>,
+    #[validate(custom(function = "crate::utils::validation::check_firstname"))]
+    pub firstname: Option<String>,
+    #[validate(custom(function = "crate::utils::validation::check_lastname"))]
+    pub lastname: Option<String>,
+    #[validate(custom(function = "crate::utils::validation::check_phone"))]
+    pub ph: Option<String>,
+    pub password: Option<String>,
+    #[validate(custom(function = "crate::utils::validation::check_username"))]
+    pub username: Option<String>,
+}
+
+impl MultipartFields for ProfileEdit {
+    const FILES: &'static [FileLimits] = &[FileLimits::images("profile", 1)];
 }
 
 #[derive(Serialize, Deserialize,Clone)]
@@ -43,6 +105,7 @@ pub struct Claims {
     pub firstname: String,
     pub lastname: String,
     pub exp: usize,
+    pub iat: usize,
 }
 
 #[derive(Serialize, Queryable)]
@@ -53,24 +116,120 @@ pub struct UserData {
     pub email: String,
     pub ph: String,
     pub profile: String,
+    pub username: Option<String>,
+    pub follower_count: i64,
+    pub following_count: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = follows)]
+pub struct NewFollow {
+    pub follower_id: i64,
+    pub followee_id: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = blocks)]
+pub struct NewBlock {
+    pub blocker_id: i64,
+    pub blocked_id: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = mutes)]
+pub struct NewMute {
+    pub muter_id: i64,
+    pub muted_id: i64,
 }
 
 #[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+#[derive(Deserialize, Validate)]
 pub struct ChangePasswordForm {
+    #[validate(length(min = 1, code = "required", message = "Old password is required"))]
     pub old_password: String,
+    #[validate(length(min = 1, code = "required", message = "New password is required"))]
     pub new_password: String,
 }
 
 // POST MODELS 
 
+/// Text fields of a new post; its images come as `postImgs` files.
+#[derive(Deserialize, Validate)]
+pub struct PostForm {
+    #[serde(default)]
+    #[validate(length(min = 2, max = 100, code = "invalid_length", message = "Name length must be between 2 and 100 characters"))]
+    pub name: String,
+    #[serde(default)]
+    #[validate(length(min = 3, max = 500, code = "invalid_length", message = "Description length must be between 3 and 500 characters"))]
+    pub description: String,
+    #[serde(default = "default_post_visibility")]
+    #[validate(custom(function = "crate::utils::validation::check_visibility"))]
+    pub visibility: String,
+    #[serde(default = "default_post_status")]
+    #[validate(custom(function = "crate::utils::validation::check_new_post_status"))]
+    pub status: String,
+    #[validate(custom(function = "crate::utils::validation::check_publish_at"))]
+    pub publish_at: Option<String>,
+}
+
+fn default_post_visibility() -> String {
+    "public".to_string()
+}
+
+fn default_post_status() -> String {
+    POST_STATUS_PUBLISHED.to_string()
+}
+
+impl MultipartFields for PostForm {
+    const FILES: &'static [FileLimits] = &[FileLimits::images("postImgs", 10)];
+}
+
+/// Fields an edit may change; absent ones are left alone.
+#[derive(Deserialize, Validate)]
+pub struct PostUpdateForm {
+    #[validate(length(min = 2, max = 100, code = "invalid_length", message = "Name length must be between 2 and 100 characters"))]
+    pub name: Option<String>,
+    #[validate(length(min = 3, max = 500, code = "invalid_length", message = "Description length must be between 3 and 500 characters"))]
+    pub description: Option<String>,
+    #[validate(custom(function = "crate::utils::validation::check_visibility"))]
+    pub visibility: Option<String>,
+    /// Storage keys of images to drop from the post.
+    #[serde(rename = "deleteImg", default, deserialize_with = "one_or_many")]
+    pub delete_imgs: Vec<String>,
+}
+
+impl MultipartFields for PostUpdateForm {
+    const FILES: &'static [FileLimits] = &[FileLimits::images("postImgs", 10)];
+}
+
+/// Who can see a post. `unlisted` posts are reachable by id but never listed.
+pub const POST_VISIBILITIES: [&str; 4] = ["public", "followers", "private", "unlisted"];
+
+pub const POST_STATUS_DRAFT: &str = "draft";
+pub const POST_STATUS_SCHEDULED: &str = "scheduled";
+pub const POST_STATUS_PUBLISHED: &str = "published";
+pub const POST_STATUS_ARCHIVED: &str = "archived";
+
 #[derive(Queryable, Serialize)]
 pub struct Post {
     pub id: i32,
     pub userid: i64,
     pub name: String,
     pub description: String,
-    pub imgs: Vec<Option<String>>,
     pub created_at: Option<NaiveDateTime>,
+    pub visibility: String,
+    pub status: String,
+    pub publish_at: Option<NaiveDateTime>,
+    pub published_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub hidden_at: Option<NaiveDateTime>,
 }
 
 #[derive(Insertable)]
@@ -79,17 +238,30 @@ pub struct NewPost {
     pub userid: i64,
     pub name: String,
     pub description: String,
-    pub imgs: Vec<Option<String>>,
+    pub visibility: String,
+    pub status: String,
+    pub publish_at: Option<NaiveDateTime>,
+    pub published_at: Option<NaiveDateTime>,
 }
 
-#[derive(Serialize, Queryable)]
+/// `imgs` lists the storage keys in display order; `images` carries the
+/// per-image metadata.
+#[derive(Serialize)]
 pub struct PostData {
     pub id: i32,
     pub userid: i64,
     pub name: String,
     pub description: String,
-    pub imgs: Vec<Option<String>>,
+    pub imgs: Vec<String>,
+    pub images: Vec<PostImage>,
+    pub visibility: String,
+    pub status: String,
+    pub publish_at: Option<NaiveDateTime>,
+    pub published_at: Option<NaiveDateTime>,
     pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub edited: bool,
+    pub entities: Vec<Entity>,
 }
 
 #[derive(Serialize)]
@@ -102,12 +274,383 @@ pub struct PostWithUser {
     pub profile: String,
     pub name: String,
     pub imgs: Vec<String>,
+    pub images: Vec<PostImage>,
     pub description: String,
+    pub visibility: String,
+    pub status: String,
+    pub published_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub edited: bool,
+    pub entities: Vec<Entity>,
+}
+
+#[derive(Queryable, Serialize, Clone)]
+pub struct PostImage {
+    pub id: i32,
+    pub post_id: i32,
+    pub storage_key: String,
+    pub position: i32,
+    pub alt_text: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub mime: String,
+    pub bytes: i64,
     pub created_at: Option<NaiveDateTime>,
 }
 
+#[derive(Insertable)]
+#[diesel(table_name = post_images)]
+pub struct NewPostImage {
+    pub post_id: i32,
+    pub storage_key: String,
+    pub position: i32,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub mime: String,
+    pub bytes: i64,
+}
+
+/// A stored version of a post; `images` are storage keys in display order.
+#[derive(Queryable, Serialize)]
+pub struct PostRevision {
+    pub id: i32,
+    pub post_id: i32,
+    pub revision: i32,
+    pub editor_id: Option<i64>,
+    pub name: String,
+    pub description: String,
+    pub images: Vec<Option<String>>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_revisions)]
+pub struct NewPostRevision {
+    pub post_id: i32,
+    pub revision: i32,
+    pub editor_id: Option<i64>,
+    pub name: String,
+    pub description: String,
+    pub images: Vec<Option<String>>,
+}
+
 #[derive(Deserialize)]
-pub struct UpdatePostFields {
-    pub name: Option<String>,
-    pub description: Option<String>,
-}
\ No newline at end of file
+pub struct RevisionDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleRequest {
+    pub publish_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderImagesRequest {
+    pub image_ids: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct AltTextRequest {
+    pub alt_text: Option<String>,
+}
+
+// UPLOAD MODELS
+
+/// Metadata for a stored image. `storage_key` is the generated filename on
+/// disk, `original_name` is what the client sent.
+#[derive(Insertable)]
+#[diesel(table_name = uploads)]
+pub struct NewUpload {
+    pub storage_key: String,
+    pub original_name: String,
+    pub mime: String,
+    pub bytes: i64,
+}
+
+// NOTIFICATION MODELS
+
+pub const NOTIFICATION_FOLLOW: &str = "follow";
+pub const NOTIFICATION_MENTION: &str = "mention";
+pub const NOTIFICATION_KINDS: [&str; 2] = [NOTIFICATION_FOLLOW, NOTIFICATION_MENTION];
+
+#[derive(Queryable, Serialize)]
+pub struct Notification {
+    pub id: i64,
+    pub user_id: i64,
+    pub actor_id: Option<i64>,
+    pub kind: String,
+    pub post_id: Option<i32>,
+    pub read_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = notifications)]
+pub struct NewNotification {
+    pub user_id: i64,
+    pub actor_id: Option<i64>,
+    pub kind: String,
+    pub post_id: Option<i32>,
+}
+
+#[derive(Queryable, Insertable, Serialize)]
+#[diesel(table_name = notification_preferences)]
+pub struct NotificationPreference {
+    pub user_id: i64,
+    pub kind: String,
+    pub in_app: bool,
+    pub email: bool,
+    pub push: bool,
+}
+
+#[derive(Deserialize)]
+pub struct MarkReadRequest {
+    /// Notifications to mark; all unread ones when absent.
+    pub ids: Option<Vec<i64>>,
+}
+
+#[derive(Deserialize)]
+pub struct PreferenceUpdate {
+    pub kind: String,
+    pub in_app: Option<bool>,
+    pub email: Option<bool>,
+    pub push: Option<bool>,
+}
+
+// MESSAGE MODELS
+
+#[derive(Queryable, Serialize)]
+pub struct Conversation {
+    pub id: i32,
+    pub created_by: Option<i64>,
+    pub created_at: Option<NaiveDateTime>,
+    pub last_message_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = conversation_members)]
+pub struct NewConversationMember {
+    pub conversation_id: i32,
+    pub user_id: i64,
+}
+
+/// `images` are storage keys, fetched through `/conversation/{id}/images/{key}`.
+#[derive(Queryable, Serialize)]
+pub struct Message {
+    pub id: i64,
+    pub conversation_id: i32,
+    pub sender_id: i64,
+    pub body: String,
+    pub images: Vec<Option<String>>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Text of a new message; up to four `images` may come with it.
+#[derive(Deserialize)]
+pub struct MessageForm {
+    #[serde(default)]
+    pub body: String,
+}
+
+impl MultipartFields for MessageForm {
+    const FILES: &'static [FileLimits] = &[FileLimits::images("images", 4)];
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = messages)]
+pub struct NewMessage {
+    pub conversation_id: i32,
+    pub sender_id: i64,
+    pub body: String,
+    pub images: Vec<Option<String>>,
+}
+
+#[derive(Serialize)]
+pub struct ConversationSummary {
+    pub id: i32,
+    pub members: Vec<UserData>,
+    pub last_message: Option<Message>,
+    pub unread: i64,
+    pub last_message_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct StartConversationRequest {
+    /// Everyone to talk to besides the caller.
+    pub member_ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct MarkConversationReadRequest {
+    /// Newest message seen; the latest one when absent.
+    pub message_id: Option<i64>,
+}
+
+// MODERATION MODELS
+
+pub const ROLE_USER: &str = "user";
+pub const ROLE_MODERATOR: &str = "moderator";
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLES: [&str; 3] = [ROLE_USER, ROLE_MODERATOR, ROLE_ADMIN];
+
+pub const REPORT_TARGET_POST: &str = "post";
+pub const REPORT_TARGET_USER: &str = "user";
+pub const REPORT_REASONS: [&str; 6] = [
+    "spam",
+    "harassment",
+    "hate",
+    "nudity",
+    "violence",
+    "other",
+];
+
+pub const REPORT_STATUS_OPEN: &str = "open";
+pub const REPORT_STATUS_RESOLVED: &str = "resolved";
+pub const REPORT_STATUS_DISMISSED: &str = "dismissed";
+
+/// Decisions a moderator can take on a report.
+pub const MODERATION_RESOLVE: &str = "resolve";
+pub const MODERATION_DISMISS: &str = "dismiss";
+pub const MODERATION_HIDE_POST: &str = "hide_post";
+pub const MODERATION_SUSPEND_USER: &str = "suspend_user";
+pub const MODERATION_DECISIONS: [&str; 4] = [
+    MODERATION_RESOLVE,
+    MODERATION_DISMISS,
+    MODERATION_HIDE_POST,
+    MODERATION_SUSPEND_USER,
+];
+pub const MODERATION_SET_ROLE: &str = "set_role";
+pub const MODERATION_SET_STATUS: &str = "set_status";
+pub const MODERATION_RESET_PASSWORD: &str = "reset_password";
+
+#[derive(Queryable, Serialize)]
+pub struct Report {
+    pub id: i64,
+    pub reporter_id: Option<i64>,
+    pub target_type: String,
+    pub target_id: i64,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub resolved_at: Option<NaiveDateTime>,
+    pub resolved_by: Option<i64>,
+}
+
+#[derive(Insertable, Deserialize)]
+#[diesel(table_name = reports)]
+pub struct NewReport {
+    #[serde(skip)]
+    pub reporter_id: Option<i64>,
+    pub target_type: String,
+    pub target_id: i64,
+    pub reason: String,
+    pub details: Option<String>,
+}
+
+#[derive(Queryable, Serialize)]
+pub struct ModerationAction {
+    pub id: i64,
+    pub moderator_id: Option<i64>,
+    pub report_id: Option<i64>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: i64,
+    pub note: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = moderation_actions)]
+pub struct NewModerationAction {
+    pub moderator_id: Option<i64>,
+    pub report_id: Option<i64>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: i64,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ModerationDecision {
+    pub action: String,
+    pub note: Option<String>,
+    /// Length of a `suspend_user` suspension.
+    pub suspend_days: Option<i64>,
+    /// Shown to the suspended user; defaults to the report reason.
+    pub suspend_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RoleUpdate {
+    pub role: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordReset {
+    pub new_password: String,
+}
+
+/// Account standing. Suspended accounts are active again once
+/// `suspended_until` has passed; the other states last until changed.
+pub const USER_STATUS_ACTIVE: &str = "active";
+pub const USER_STATUS_SUSPENDED: &str = "suspended";
+pub const USER_STATUS_BANNED: &str = "banned";
+pub const USER_STATUS_DEACTIVATED: &str = "deactivated";
+pub const USER_STATUSES: [&str; 4] = [
+    USER_STATUS_ACTIVE,
+    USER_STATUS_SUSPENDED,
+    USER_STATUS_BANNED,
+    USER_STATUS_DEACTIVATED,
+];
+
+#[derive(Deserialize)]
+pub struct StatusUpdate {
+    pub status: String,
+    /// Required for `suspended`.
+    pub suspended_until: Option<NaiveDateTime>,
+    pub reason: Option<String>,
+}
+
+/// Actions written to the audit log.
+pub const AUDIT_LOGIN: &str = "login";
+pub const AUDIT_LOGIN_FAILED: &str = "login_failed";
+pub const AUDIT_PASSWORD_CHANGED: &str = "password_changed";
+pub const AUDIT_PROFILE_UPDATED: &str = "profile_updated";
+pub const AUDIT_POST_DELETED: &str = "post_deleted";
+pub const AUDIT_MODERATION_DECISION: &str = "moderation_decision";
+pub const AUDIT_ROLE_CHANGED: &str = "role_changed";
+pub const AUDIT_STATUS_CHANGED: &str = "status_changed";
+
+pub const AUDIT_TARGET_USER: &str = "user";
+pub const AUDIT_TARGET_POST: &str = "post";
+pub const AUDIT_TARGET_REPORT: &str = "report";
+
+#[derive(Queryable, Serialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub actor_id: Option<i64>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_events)]
+pub struct NewAuditEvent {
+    pub actor_id: Option<i64>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<i64>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
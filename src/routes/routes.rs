@@ -1,8 +1,40 @@
+use crate::handlers::audit_handler::list_audit_events;
+use crate::handlers::block_handler::{
+    block_user, get_blocked_users, get_muted_users, mute_user, unblock_user, unmute_user,
+};
+use crate::handlers::export_handler::export_user_data;
+use crate::handlers::follow_handler::{follow_user, get_followers, get_following, unfollow_user};
+use crate::handlers::message_handler::{
+    get_message_image, list_conversations, list_messages, mark_conversation_read, send_message,
+    start_conversation,
+};
+use crate::handlers::moderation_handler::{
+    create_report, decide_report, list_moderation_actions, list_reports, reset_user_password,
+    set_user_role, set_user_status,
+};
+use crate::handlers::notification_handler::{
+    get_notification_preferences, list_notifications, mark_notifications_read,
+    unread_notification_count, update_notification_preferences,
+};
 use crate::handlers::post_handler::{
-    delete_post, get_all_posts, get_post_by_id, update_post, upload_post,
+    archive_post, delete_post, get_all_posts, get_feed, get_post_by_id, publish_post, update_post,
+    upload_post,
+};
+use crate::handlers::post_image_handler::{
+    delete_post_image, get_post_image, reorder_post_images, set_image_alt_text,
+};
+use crate::handlers::revision_handler::{
+    diff_post_revisions, list_post_revisions, restore_post_revision,
+};
+use crate::handlers::schedule_handler::{
+    cancel_scheduled_post, list_scheduled_posts, reschedule_post,
 };
+use crate::handlers::stream_handler::stream_events;
+use crate::handlers::tag_handler::{get_posts_by_tag, get_trending_tags};
+use crate::handlers::trash_handler::{list_trashed_posts, restore_post};
 use crate::handlers::user_handler::{
-    change_password, get_all_users, get_user_by_id, login_user, register_user, update_user,
+    change_password, delete_user, get_all_users, get_user_by_id, login_user, register_user,
+    restore_account, update_user,
 };
 use crate::utils::auth::AuthMiddlewareFactory;
 use actix_web::web;
@@ -10,17 +42,112 @@ use actix_web::web;
 pub fn user_routes(cfg: &mut web::ServiceConfig) {
     cfg.route("/register", web::post().to(register_user))
         .route("/login", web::post().to(login_user))
+        .route("/restoreAccount", web::post().to(restore_account))
+        .route("/stream", web::get().to(stream_events))
         .service(
             web::scope("")
                 .wrap(AuthMiddlewareFactory)
                 .route("/users", web::get().to(get_all_users))
                 .route("/user/{id}", web::get().to(get_user_by_id))
                 .route("/user/{id}", web::put().to(update_user))
+                .route("/user/{id}", web::delete().to(delete_user))
+                .route("/user/{id}/export", web::get().to(export_user_data))
+                .route("/user/{id}/follow", web::post().to(follow_user))
+                .route("/user/{id}/follow", web::delete().to(unfollow_user))
+                .route("/user/{id}/block", web::post().to(block_user))
+                .route("/user/{id}/block", web::delete().to(unblock_user))
+                .route("/user/{id}/mute", web::post().to(mute_user))
+                .route("/user/{id}/mute", web::delete().to(unmute_user))
+                .route("/blocks", web::get().to(get_blocked_users))
+                .route("/mutes", web::get().to(get_muted_users))
+                .route("/user/{id}/followers", web::get().to(get_followers))
+                .route("/user/{id}/following", web::get().to(get_following))
                 .route("/post", web::post().to(upload_post))
                 .route("/allPost", web::get().to(get_all_posts))
+                .route("/feed", web::get().to(get_feed))
+                .route("/tag/{name}/posts", web::get().to(get_posts_by_tag))
+                .route("/tags/trending", web::get().to(get_trending_tags))
                 .route("/post/{id}", web::get().to(get_post_by_id))
                 .route("/deletePost/{id}", web::delete().to(delete_post))
                 .route("/updatePost/{id}", web::put().to(update_post))
+                .route("/post/{id}/publish", web::post().to(publish_post))
+                .route("/post/{id}/archive", web::post().to(archive_post))
+                .route("/post/{id}/restore", web::post().to(restore_post))
+                .route("/trash", web::get().to(list_trashed_posts))
+                .route("/post/{id}/revisions", web::get().to(list_post_revisions))
+                .route(
+                    "/post/{id}/revisions/diff",
+                    web::get().to(diff_post_revisions),
+                )
+                .route(
+                    "/post/{id}/revisions/{revision}/restore",
+                    web::post().to(restore_post_revision),
+                )
+                .route("/scheduledPosts", web::get().to(list_scheduled_posts))
+                .route("/post/{id}/schedule", web::put().to(reschedule_post))
+                .route(
+                    "/post/{id}/schedule",
+                    web::delete().to(cancel_scheduled_post),
+                )
+                .route(
+                    "/post/{id}/images/order",
+                    web::put().to(reorder_post_images),
+                )
+                .route(
+                    "/post/{id}/images/{image_id}",
+                    web::put().to(set_image_alt_text),
+                )
+                .route("/post/{id}/images/{key}", web::get().to(get_post_image))
+                .route(
+                    "/post/{id}/images/{image_id}",
+                    web::delete().to(delete_post_image),
+                )
+                .route("/notifications", web::get().to(list_notifications))
+                .route(
+                    "/notifications/unreadCount",
+                    web::get().to(unread_notification_count),
+                )
+                .route(
+                    "/notifications/read",
+                    web::post().to(mark_notifications_read),
+                )
+                .route(
+                    "/notifications/preferences",
+                    web::get().to(get_notification_preferences),
+                )
+                .route(
+                    "/notifications/preferences",
+                    web::put().to(update_notification_preferences),
+                )
+                .route("/conversations", web::post().to(start_conversation))
+                .route("/conversations", web::get().to(list_conversations))
+                .route("/conversation/{id}/messages", web::get().to(list_messages))
+                .route("/conversation/{id}/messages", web::post().to(send_message))
+                .route(
+                    "/conversation/{id}/read",
+                    web::post().to(mark_conversation_read),
+                )
+                .route(
+                    "/conversation/{id}/images/{key}",
+                    web::get().to(get_message_image),
+                )
+                .route("/reports", web::post().to(create_report))
+                .route("/moderation/reports", web::get().to(list_reports))
+                .route(
+                    "/moderation/reports/{id}/decision",
+                    web::post().to(decide_report),
+                )
+                .route(
+                    "/moderation/actions",
+                    web::get().to(list_moderation_actions),
+                )
+                .route("/admin/users/{id}/role", web::put().to(set_user_role))
+                .route("/admin/users/{id}/status", web::put().to(set_user_status))
+                .route(
+                    "/admin/users/{id}/password",
+                    web::put().to(reset_user_password),
+                )
+                .route("/admin/audit", web::get().to(list_audit_events))
                 .route("/changePassword/{id}", web::put().to(change_password)),
         );
 }
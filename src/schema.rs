@@ -1,5 +1,162 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_events (id) {
+        id -> Int8,
+        actor_id -> Nullable<Int8>,
+        #[max_length = 40]
+        action -> Varchar,
+        #[max_length = 20]
+        target_type -> Nullable<Varchar>,
+        target_id -> Nullable<Int8>,
+        #[max_length = 64]
+        ip -> Nullable<Varchar>,
+        user_agent -> Nullable<Text>,
+        before -> Nullable<Jsonb>,
+        after -> Nullable<Jsonb>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    blocks (blocker_id, blocked_id) {
+        blocker_id -> Int8,
+        blocked_id -> Int8,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    conversation_members (conversation_id, user_id) {
+        conversation_id -> Int4,
+        user_id -> Int8,
+        last_read_message_id -> Nullable<Int8>,
+        joined_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    conversations (id) {
+        id -> Int4,
+        created_by -> Nullable<Int8>,
+        created_at -> Nullable<Timestamp>,
+        last_message_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    follows (follower_id, followee_id) {
+        follower_id -> Int8,
+        followee_id -> Int8,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    mentions (post_id, user_id) {
+        post_id -> Int4,
+        user_id -> Int8,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    messages (id) {
+        id -> Int8,
+        conversation_id -> Int4,
+        sender_id -> Int8,
+        body -> Text,
+        images -> Array<Nullable<Text>>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    moderation_actions (id) {
+        id -> Int8,
+        moderator_id -> Nullable<Int8>,
+        report_id -> Nullable<Int8>,
+        #[max_length = 30]
+        action -> Varchar,
+        #[max_length = 20]
+        target_type -> Varchar,
+        target_id -> Int8,
+        note -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    mutes (muter_id, muted_id) {
+        muter_id -> Int8,
+        muted_id -> Int8,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    notification_preferences (user_id, kind) {
+        user_id -> Int8,
+        #[max_length = 30]
+        kind -> Varchar,
+        in_app -> Bool,
+        email -> Bool,
+        push -> Bool,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Int8,
+        user_id -> Int8,
+        actor_id -> Nullable<Int8>,
+        #[max_length = 30]
+        kind -> Varchar,
+        post_id -> Nullable<Int4>,
+        read_at -> Nullable<Timestamp>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    post_images (id) {
+        id -> Int4,
+        post_id -> Int4,
+        #[max_length = 255]
+        storage_key -> Varchar,
+        position -> Int4,
+        #[max_length = 500]
+        alt_text -> Nullable<Varchar>,
+        width -> Nullable<Int4>,
+        height -> Nullable<Int4>,
+        #[max_length = 100]
+        mime -> Varchar,
+        bytes -> Int8,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    post_revisions (id) {
+        id -> Int4,
+        post_id -> Int4,
+        revision -> Int4,
+        editor_id -> Nullable<Int8>,
+        #[max_length = 100]
+        name -> Varchar,
+        description -> Text,
+        images -> Array<Nullable<Text>>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    post_tags (post_id, tag_id) {
+        post_id -> Int4,
+        tag_id -> Int4,
+    }
+}
+
 diesel::table! {
     posts (id) {
         id -> Int4,
@@ -7,7 +164,56 @@ diesel::table! {
         #[max_length = 100]
         name -> Varchar,
         description -> Text,
-        imgs -> Array<Nullable<Text>>,
+        created_at -> Nullable<Timestamp>,
+        #[max_length = 20]
+        visibility -> Varchar,
+        #[max_length = 20]
+        status -> Varchar,
+        publish_at -> Nullable<Timestamp>,
+        published_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+        deleted_at -> Nullable<Timestamp>,
+        hidden_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    reports (id) {
+        id -> Int8,
+        reporter_id -> Nullable<Int8>,
+        #[max_length = 20]
+        target_type -> Varchar,
+        target_id -> Int8,
+        #[max_length = 30]
+        reason -> Varchar,
+        details -> Nullable<Text>,
+        #[max_length = 20]
+        status -> Varchar,
+        created_at -> Nullable<Timestamp>,
+        resolved_at -> Nullable<Timestamp>,
+        resolved_by -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Int4,
+        #[max_length = 100]
+        name -> Varchar,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    uploads (id) {
+        id -> Int4,
+        #[max_length = 255]
+        storage_key -> Varchar,
+        #[max_length = 255]
+        original_name -> Varchar,
+        #[max_length = 100]
+        mime -> Varchar,
+        bytes -> Int8,
         created_at -> Nullable<Timestamp>,
     }
 }
@@ -29,9 +235,55 @@ diesel::table! {
         password -> Varchar,
         created_at -> Nullable<Timestamp>,
         updated_at -> Nullable<Timestamp>,
+        deleted_at -> Nullable<Timestamp>,
+        #[max_length = 30]
+        username -> Nullable<Varchar>,
+        #[max_length = 20]
+        role -> Varchar,
+        suspended_until -> Nullable<Timestamp>,
+        suspension_reason -> Nullable<Text>,
+        #[max_length = 20]
+        status -> Varchar,
+        status_changed_at -> Nullable<Timestamp>,
+        password_changed_at -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(conversation_members -> conversations (conversation_id));
+diesel::joinable!(conversation_members -> users (user_id));
+diesel::joinable!(conversations -> users (created_by));
+diesel::joinable!(mentions -> posts (post_id));
+diesel::joinable!(mentions -> users (user_id));
+diesel::joinable!(messages -> conversations (conversation_id));
+diesel::joinable!(messages -> users (sender_id));
+diesel::joinable!(moderation_actions -> reports (report_id));
+diesel::joinable!(moderation_actions -> users (moderator_id));
+diesel::joinable!(notification_preferences -> users (user_id));
+diesel::joinable!(notifications -> posts (post_id));
+diesel::joinable!(post_images -> posts (post_id));
+diesel::joinable!(post_revisions -> posts (post_id));
+diesel::joinable!(post_tags -> posts (post_id));
+diesel::joinable!(post_tags -> tags (tag_id));
 diesel::joinable!(posts -> users (userid));
 
-diesel::allow_tables_to_appear_in_same_query!(posts, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    audit_events,
+    blocks,
+    conversation_members,
+    conversations,
+    follows,
+    mentions,
+    messages,
+    moderation_actions,
+    mutes,
+    notification_preferences,
+    notifications,
+    post_images,
+    post_revisions,
+    post_tags,
+    posts,
+    reports,
+    tags,
+    uploads,
+    users,
+);
@@ -0,0 +1,126 @@
+use actix_web::HttpResponse;
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::{
+    models::user::{
+        USER_STATUS_ACTIVE, USER_STATUS_BANNED, USER_STATUS_DEACTIVATED, USER_STATUS_SUSPENDED,
+    },
+    schema::users,
+    utils::auth::Claims,
+};
+
+/// The status that applies right now: a suspension whose end has passed
+/// counts as active.
+pub fn effective_status(status: &str, suspended_until: Option<NaiveDateTime>) -> &str {
+    match (status, suspended_until) {
+        (USER_STATUS_SUSPENDED, Some(until)) if until <= Utc::now().naive_utc() => {
+            USER_STATUS_ACTIVE
+        }
+        _ => status,
+    }
+}
+
+/// 403 explaining why an account in `status` can't be used.
+pub fn refusal(
+    status: &str,
+    suspended_until: Option<NaiveDateTime>,
+    reason: Option<&str>,
+) -> HttpResponse {
+    let body = match status {
+        USER_STATUS_SUSPENDED => serde_json::json!({
+            "status": false,
+            "message": "This account is suspended",
+            "account_status": status,
+            "suspended_until": suspended_until,
+            "reason": reason
+        }),
+        USER_STATUS_BANNED => serde_json::json!({
+            "status": false,
+            "message": "This account is banned",
+            "account_status": status,
+            "reason": reason
+        }),
+        USER_STATUS_DEACTIVATED => serde_json::json!({
+            "status": false,
+            "message": "This account is deactivated",
+            "account_status": status
+        }),
+        _ => serde_json::json!({
+            "status": false,
+            "message": "This account can't be used",
+            "account_status": status
+        }),
+    };
+    HttpResponse::Forbidden().json(body)
+}
+
+/// Refuse a token when its account is deleted or not active, or when the
+/// account lost its standing or had its password set after the token was
+/// issued (a lapsed suspension or a restore does not bring older tokens back).
+#[allow(clippy::result_large_err)]
+pub fn check_token_standing(conn: &mut PgConnection, claims: &Claims) -> Result<(), HttpResponse> {
+    let account = users::table
+        .filter(users::id.eq(claims.id))
+        .select((
+            users::status,
+            users::suspended_until,
+            users::suspension_reason,
+            users::status_changed_at,
+            users::password_changed_at,
+            users::deleted_at,
+        ))
+        .first::<(
+            String,
+            Option<NaiveDateTime>,
+            Option<String>,
+            Option<NaiveDateTime>,
+            Option<NaiveDateTime>,
+            Option<NaiveDateTime>,
+        )>(conn)
+        .optional();
+
+    let (status, suspended_until, reason, changed_at, password_changed_at, deleted_at) =
+        match account {
+            Ok(Some(account)) => account,
+            Ok(None) => {
+                return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                    "status": false,
+                    "message": "Unauthorized: account no longer exists"
+                })));
+            }
+            Err(e) => {
+                eprintln!("Database query failed: {}", e);
+                return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": false,
+                    "message": "Database error",
+                    "error": e.to_string()
+                })));
+            }
+        };
+
+    if deleted_at.is_some() {
+        return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "status": false,
+            "message": "Unauthorized: account was deleted"
+        })));
+    }
+
+    let current = effective_status(&status, suspended_until);
+    if current != USER_STATUS_ACTIVE {
+        return Err(refusal(current, suspended_until, reason.as_deref()));
+    }
+
+    let issued_before_change = [changed_at, password_changed_at]
+        .into_iter()
+        .flatten()
+        .any(|changed| (claims.iat as i64) < changed.and_utc().timestamp());
+    if issued_before_change {
+        return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "status": false,
+            "message": "Token was revoked; please log in again"
+        })));
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use actix_web::HttpRequest;
+use actix_web::http::header::USER_AGENT;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+
+use crate::{models::user::NewAuditEvent, schema::audit_events};
+
+/// Longest user agent kept; anything past it is cut off.
+const USER_AGENT_LIMIT: usize = 512;
+
+/// The address of the caller. `Forwarded` / `X-Forwarded-For` are only
+/// believed when the connection comes from a proxy listed in
+/// `TRUSTED_PROXIES` (comma separated IPs); anyone else could forge them.
+fn client_ip(req: &HttpRequest) -> Option<String> {
+    let peer = req.peer_addr()?.ip();
+    let trusted = std::env::var("TRUSTED_PROXIES").is_ok_and(|proxies| {
+        proxies
+            .split(',')
+            .filter_map(|p| p.trim().parse::<IpAddr>().ok())
+            .any(|proxy| proxy == peer)
+    });
+    if trusted {
+        req.connection_info()
+            .realip_remote_addr()
+            .map(str::to_string)
+            .or_else(|| Some(peer.to_string()))
+    } else {
+        Some(peer.to_string())
+    }
+}
+
+/// An audit entry for `action` on `target`, stamped with the caller's IP and
+/// user agent. Fill in `before`/`after` with [`changes`] where it applies.
+pub fn event(
+    req: &HttpRequest,
+    actor_id: Option<i64>,
+    action: &str,
+    target: Option<(&str, i64)>,
+) -> NewAuditEvent {
+    let ip = client_ip(req).map(|ip| ip.chars().take(64).collect());
+    let user_agent = req
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .map(|ua| ua.chars().take(USER_AGENT_LIMIT).collect());
+
+    NewAuditEvent {
+        actor_id,
+        action: action.to_string(),
+        target_type: target.map(|(kind, _)| kind.to_string()),
+        target_id: target.map(|(_, id)| id),
+        ip,
+        user_agent,
+        before: None,
+        after: None,
+    }
+}
+
+/// The fields of two JSON objects that differ, as `(before, after)`.
+/// `None` on both sides when nothing changed.
+pub fn changes(before: Value, after: Value) -> (Option<Value>, Option<Value>) {
+    let (Value::Object(before), Value::Object(after)) = (before, after) else {
+        return (None, None);
+    };
+
+    let mut old = Map::new();
+    let mut new = Map::new();
+    for (key, value) in &after {
+        let previous = before.get(key).unwrap_or(&Value::Null);
+        if previous != value {
+            old.insert(key.clone(), previous.clone());
+            new.insert(key.clone(), value.clone());
+        }
+    }
+    for (key, value) in &before {
+        if !after.contains_key(key) {
+            old.insert(key.clone(), value.clone());
+            new.insert(key.clone(), Value::Null);
+        }
+    }
+
+    if new.is_empty() {
+        (None, None)
+    } else {
+        (Some(Value::Object(old)), Some(Value::Object(new)))
+    }
+}
+
+/// Append an entry to the audit log. Failures are logged and never undo the
+/// action being recorded.
+pub fn record(conn: &mut PgConnection, event: NewAuditEvent) {
+    if let Err(e) = diesel::insert_into(audit_events::table)
+        .values(&event)
+        .execute(conn)
+    {
+        eprintln!("Audit event {} failed: {}", event.action, e);
+    }
+}
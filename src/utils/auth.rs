@@ -1,6 +1,6 @@
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpResponse,
+    web, Error, HttpMessage, HttpRequest, HttpResponse,
 };
 use futures_util::future::{ok, LocalBoxFuture, Ready};
 use jsonwebtoken::{decode, DecodingKey, Validation};
@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 use std::{env, rc::Rc};
 use actix_web::body::EitherBody;
 
+use crate::{db::Pool, utils::account_status::check_token_standing};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub id: i64,
@@ -15,6 +17,31 @@ pub struct Claims {
     pub firstname: String,
     pub lastname: String,
     pub exp: usize,
+    /// Issue time; tokens from before a suspension or ban are refused.
+    #[serde(default)]
+    pub iat: usize,
+}
+
+/// Claims inserted by `AuthMiddleware`, or a 401 response when missing.
+#[allow(clippy::result_large_err)]
+pub fn require_claims(req: &HttpRequest) -> Result<Claims, HttpResponse> {
+    req.extensions().get::<Claims>().cloned().ok_or_else(|| {
+        HttpResponse::Unauthorized().json(serde_json::json!({
+            "status": false,
+            "message": "Unauthorized: No valid token found"
+        }))
+    })
+}
+
+/// Validate a bearer token and return its claims.
+pub fn decode_claims(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "supersecretkey".to_string());
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map(|token_data| token_data.claims)
 }
 
 pub struct AuthMiddlewareFactory;
@@ -76,18 +103,19 @@ where
             }
 
             let token = token_opt.unwrap();
-            let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "supersecretkey".to_string());
 
             // Decode token
-            let decoded = decode::<Claims>(
-                &token,
-                &DecodingKey::from_secret(secret.as_bytes()),
-                &Validation::default(),
-            );
-
-            match decoded {
-                Ok(token_data) => {
-                    req.extensions_mut().insert(token_data.claims);
+            match decode_claims(&token) {
+                Ok(claims) => {
+                    // Account standing is checked on every request so suspensions apply at once
+                    if let Some(pool) = req.app_data::<web::Data<Pool>>() {
+                        let mut conn = pool.get().expect("DB connection error");
+                        if let Err(response) = check_token_standing(&mut conn, &claims) {
+                            return Ok(req.into_response(response.map_into_right_body()));
+                        }
+                    }
+
+                    req.extensions_mut().insert(claims);
                     let res = service.call(req).await?;
                     Ok(res.map_into_left_body()) 
                 }
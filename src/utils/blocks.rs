@@ -0,0 +1,85 @@
+use diesel::dsl::{Eq, Filter, IntoBoxed, Select, exists};
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+
+use crate::{
+    schema::{blocks, mutes, users},
+    utils::soft_delete::{LiveUsers, live_users},
+};
+
+pub type BlockedBy = Select<Filter<blocks::table, Eq<blocks::blocker_id, i64>>, blocks::blocked_id>;
+pub type BlockersOf =
+    Select<Filter<blocks::table, Eq<blocks::blocked_id, i64>>, blocks::blocker_id>;
+pub type MutedBy = Select<Filter<mutes::table, Eq<mutes::muter_id, i64>>, mutes::muted_id>;
+pub type UserListing<'a> = IntoBoxed<'a, LiveUsers, Pg>;
+
+/// Users `user_id` has blocked, as a subquery.
+pub fn blocked_by(user_id: i64) -> BlockedBy {
+    blocks::table
+        .filter(blocks::blocker_id.eq(user_id))
+        .select(blocks::blocked_id)
+}
+
+/// Users who blocked `user_id`, as a subquery.
+pub fn blockers_of(user_id: i64) -> BlockersOf {
+    blocks::table
+        .filter(blocks::blocked_id.eq(user_id))
+        .select(blocks::blocker_id)
+}
+
+/// Users `user_id` has muted, as a subquery.
+pub fn muted_by(user_id: i64) -> MutedBy {
+    mutes::table
+        .filter(mutes::muter_id.eq(user_id))
+        .select(mutes::muted_id)
+}
+
+/// Live users `viewer_id` may see: nobody on either side of a block.
+/// Start user listings here instead of `live_users`.
+pub fn users_visible_to<'a>(viewer_id: i64) -> UserListing<'a> {
+    live_users()
+        .filter(diesel::dsl::not(users::id.eq_any(blocked_by(viewer_id))))
+        .filter(diesel::dsl::not(users::id.eq_any(blockers_of(viewer_id))))
+        .into_boxed()
+}
+
+/// Whether `user_id` and any of `others` have blocked one another.
+pub fn blocked_between(conn: &mut PgConnection, user_id: i64, others: &[i64]) -> QueryResult<bool> {
+    diesel::select(exists(
+        blocks::table.filter(
+            blocks::blocker_id
+                .eq(user_id)
+                .and(blocks::blocked_id.eq_any(others))
+                .or(blocks::blocked_id
+                    .eq(user_id)
+                    .and(blocks::blocker_id.eq_any(others))),
+        ),
+    ))
+    .get_result::<bool>(conn)
+}
+
+/// Whether `muter_id` muted `user_id`.
+pub fn has_muted(conn: &mut PgConnection, muter_id: i64, user_id: i64) -> QueryResult<bool> {
+    diesel::select(exists(
+        mutes::table
+            .filter(mutes::muter_id.eq(muter_id))
+            .filter(mutes::muted_id.eq(user_id)),
+    ))
+    .get_result::<bool>(conn)
+}
+
+/// Everyone who should not see `author_id`'s posts show up live: both sides
+/// of their blocks and the people who muted them.
+pub fn shielded_from(conn: &mut PgConnection, author_id: i64) -> QueryResult<Vec<i64>> {
+    let mut ids = blocked_by(author_id).load::<i64>(conn)?;
+    ids.extend(blockers_of(author_id).load::<i64>(conn)?);
+    ids.extend(
+        mutes::table
+            .filter(mutes::muted_id.eq(author_id))
+            .select(mutes::muter_id)
+            .load::<i64>(conn)?,
+    );
+    ids.sort_unstable();
+    ids.dedup();
+    Ok(ids)
+}
@@ -0,0 +1,60 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+pub const ENTITY_HASHTAG: &str = "hashtag";
+pub const ENTITY_MENTION: &str = "mention";
+
+/// A `#tag` or `@user` token in a post description. `start`/`end` are
+/// character offsets into the description covering the whole token,
+/// sigil included; `text` is the lowercased name without the sigil.
+#[derive(Serialize, Clone)]
+pub struct Entity {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn entity_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    // A token must start the text or follow a non-word character, so emails and "a#b" don't match
+    RE.get_or_init(|| Regex::new(r"(?:^|[^\w])(([#@])(\w+))").unwrap())
+}
+
+/// Find hashtags and mentions in `text`. Mentions follow the username rules
+/// (ASCII letters, digits and underscores, 3-30 long); hashtags allow up to 100
+/// word characters in any script.
+pub fn parse_entities(text: &str) -> Vec<Entity> {
+    entity_regex()
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let token = caps.get(1)?;
+            let name = caps.get(3)?.as_str().to_lowercase();
+            let kind = match &caps[2] {
+                "#" if name.chars().count() <= 100 => ENTITY_HASHTAG,
+                "@" if (3..=30).contains(&name.len()) && name.is_ascii() => ENTITY_MENTION,
+                _ => return None,
+            };
+            let start = text[..token.start()].chars().count();
+            Some(Entity {
+                kind,
+                text: name,
+                start,
+                end: start + token.as_str().chars().count(),
+            })
+        })
+        .collect()
+}
+
+/// Distinct names of the entities of one kind, in order of first appearance.
+pub fn entity_names(entities: &[Entity], kind: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for entity in entities.iter().filter(|e| e.kind == kind) {
+        if !names.contains(&entity.text) {
+            names.push(entity.text.clone());
+        }
+    }
+    names
+}
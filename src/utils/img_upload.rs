@@ -1,25 +1,29 @@
-use std::fs;
-use std::io::Write;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::models::user::NewUpload;
+use crate::utils::upload::{TempUpload, remove_stored_files};
 
-pub fn save_multiple_images(files: Vec<(Vec<u8>, String)>) -> Result<Vec<String>, String> {
-    let dir = "files/userPost";
-    fs::create_dir_all(dir).map_err(|_| "Failed to create directory")?;
+pub const POST_DIR: &str = "files/userPost";
+/// Direct message images. Not mounted as static files; members fetch them
+/// through the conversation endpoints.
+pub const MESSAGE_DIR: &str = "files/messages";
 
-    let mut saved_names = Vec::new();
-    for (bytes, original_name) in files {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|_| "Failed to get unix time")?
-            .as_secs();
-        let fname = format!("{}_{}", timestamp, original_name);
-        let path = format!("{}/{}", dir, fname);
-
-        let mut f = fs::File::create(&path).map_err(|_| "Failed to create image file")?;
-        f.write_all(&bytes).map_err(|_| "Failed to write image data")?;
+pub fn save_multiple_images(files: Vec<TempUpload>) -> Result<Vec<NewUpload>, String> {
+    save_images_in(POST_DIR, files)
+}
 
-        saved_names.push(fname);
+/// Store a batch of staged images in `dir`, all or nothing.
+pub fn save_images_in(dir: &str, files: Vec<TempUpload>) -> Result<Vec<NewUpload>, String> {
+    let mut saved: Vec<NewUpload> = Vec::new();
+    for upload in files {
+        match upload.store_in(dir) {
+            Ok(stored) => saved.push(stored),
+            Err(e) => {
+                // Don't leave half of the batch behind
+                let keys: Vec<String> = saved.into_iter().map(|s| s.storage_key).collect();
+                remove_stored_files(dir, &keys);
+                return Err(e);
+            }
+        }
     }
 
-    Ok(saved_names)
+    Ok(saved)
 }
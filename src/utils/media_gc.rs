@@ -0,0 +1,265 @@
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    db::Pool,
+    schema::{messages, post_images, post_revisions, uploads, users},
+    utils::{
+        file_upload::PROFILE_DIR,
+        img_upload::{MESSAGE_DIR, POST_DIR},
+        upload::TMP_DIR,
+    },
+};
+
+#[derive(Serialize)]
+pub struct OrphanFile {
+    pub dir: String,
+    pub name: String,
+    pub age_secs: u64,
+    pub deleted: bool,
+}
+
+#[derive(Serialize)]
+pub struct DanglingRef {
+    pub table: String,
+    pub row_id: i64,
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub orphans: Vec<OrphanFile>,
+    pub dangling: Vec<DanglingRef>,
+}
+
+pub struct GcOptions {
+    pub grace: Duration,
+    pub dry_run: bool,
+}
+
+impl GcOptions {
+    /// Read `MEDIA_GC_GRACE_SECS` (default 24h); dry run stays off.
+    pub fn from_env() -> Self {
+        let grace_secs = std::env::var("MEDIA_GC_GRACE_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(24 * 60 * 60);
+        GcOptions {
+            grace: Duration::from_secs(grace_secs),
+            dry_run: false,
+        }
+    }
+}
+
+fn list_files(dir: &str) -> Vec<(String, u64)> {
+    let now = SystemTime::now();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            if !meta.is_file() {
+                return None;
+            }
+            let age = meta
+                .modified()
+                .ok()
+                .and_then(|m| now.duration_since(m).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            Some((entry.file_name().to_string_lossy().to_string(), age))
+        })
+        .collect()
+}
+
+/// Diff the upload directories against `post_images`, `post_revisions`, `messages`
+/// and `users.profile`.
+/// Orphans older than the grace period are deleted unless `dry_run` is set.
+pub fn collect_orphans(conn: &mut PgConnection, opts: &GcOptions) -> QueryResult<GcReport> {
+    let post_rows = post_images::table
+        .select((post_images::post_id, post_images::storage_key))
+        .load::<(i32, String)>(conn)?;
+    let user_rows = users::table
+        .select((users::id, users::profile))
+        .load::<(i64, String)>(conn)?;
+
+    let revision_rows = post_revisions::table
+        .select(post_revisions::images)
+        .load::<Vec<Option<String>>>(conn)?;
+
+    // Images dropped from a post stay referenced while its history shows them
+    let post_files: HashSet<String> = post_rows
+        .iter()
+        .map(|(_, key)| key.clone())
+        .chain(revision_rows.into_iter().flatten().flatten())
+        .collect();
+    let profile_files: HashSet<String> = user_rows.iter().map(|(_, p)| p.clone()).collect();
+    let message_rows: Vec<(i64, String)> = messages::table
+        .select((messages::id, messages::images))
+        .load::<(i64, Vec<Option<String>>)>(conn)?
+        .into_iter()
+        .flat_map(|(id, images)| images.into_iter().flatten().map(move |key| (id, key)))
+        .collect();
+    let message_files: HashSet<String> = message_rows.iter().map(|(_, key)| key.clone()).collect();
+
+    let mut report = GcReport {
+        dry_run: opts.dry_run,
+        orphans: Vec::new(),
+        dangling: Vec::new(),
+    };
+
+    // Anything still in the staging dir after the grace period was abandoned
+    let empty = HashSet::new();
+    for (dir, referenced) in [
+        (POST_DIR, &post_files),
+        (PROFILE_DIR, &profile_files),
+        (MESSAGE_DIR, &message_files),
+        (TMP_DIR, &empty),
+    ] {
+        for (name, age_secs) in list_files(dir) {
+            if referenced.contains(&name) {
+                continue;
+            }
+
+            let expired = age_secs >= opts.grace.as_secs();
+            let mut deleted = false;
+            if expired && !opts.dry_run {
+                match fs::remove_file(Path::new(dir).join(&name)) {
+                    Ok(_) => {
+                        deleted = true;
+                        diesel::delete(uploads::table.filter(uploads::storage_key.eq(&name)))
+                            .execute(conn)?;
+                    }
+                    Err(e) => eprintln!("Failed to delete orphan {}/{}: {}", dir, name, e),
+                }
+            }
+
+            report.orphans.push(OrphanFile {
+                dir: dir.to_string(),
+                name,
+                age_secs,
+                deleted,
+            });
+        }
+    }
+
+    for (post_id, name) in &post_rows {
+        if !Path::new(POST_DIR).join(name).exists() {
+            report.dangling.push(DanglingRef {
+                table: "post_images".to_string(),
+                row_id: *post_id as i64,
+                name: name.clone(),
+            });
+        }
+    }
+    for (message_id, name) in &message_rows {
+        if !Path::new(MESSAGE_DIR).join(name).exists() {
+            report.dangling.push(DanglingRef {
+                table: "messages".to_string(),
+                row_id: *message_id,
+                name: name.clone(),
+            });
+        }
+    }
+    for (user_id, profile) in &user_rows {
+        if !Path::new(PROFILE_DIR).join(profile).exists() {
+            report.dangling.push(DanglingRef {
+                table: "users".to_string(),
+                row_id: *user_id,
+                name: profile.clone(),
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+fn log_report(report: &GcReport) {
+    let deleted = report.orphans.iter().filter(|o| o.deleted).count();
+    println!(
+        "Media GC{}: {} orphan(s), {} deleted, {} dangling reference(s)",
+        if report.dry_run { " (dry run)" } else { "" },
+        report.orphans.len(),
+        deleted,
+        report.dangling.len()
+    );
+    for orphan in &report.orphans {
+        println!(
+            "  orphan {}/{} age={}s{}",
+            orphan.dir,
+            orphan.name,
+            orphan.age_secs,
+            if orphan.deleted { " deleted" } else { "" }
+        );
+    }
+    for dangling in &report.dangling {
+        println!(
+            "  dangling {} #{} -> {}",
+            dangling.table, dangling.row_id, dangling.name
+        );
+    }
+}
+
+/// Entry point for `rust_api gc-media [--dry-run] [--grace-secs N]`.
+pub fn run_cli(pool: &Pool, args: &[String]) -> Result<(), String> {
+    let mut opts = GcOptions::from_env();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--dry-run" => opts.dry_run = true,
+            "--grace-secs" => {
+                let secs = iter
+                    .next()
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .ok_or("--grace-secs expects a number of seconds")?;
+                opts.grace = Duration::from_secs(secs);
+            }
+            other => return Err(format!("Unknown option: {}", other)),
+        }
+    }
+
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let report = collect_orphans(&mut conn, &opts).map_err(|e| e.to_string())?;
+    log_report(&report);
+    Ok(())
+}
+
+/// Run the collector periodically when `MEDIA_GC_INTERVAL_SECS` is set.
+pub fn spawn_background(pool: Pool) {
+    let interval_secs = match std::env::var("MEDIA_GC_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        Some(secs) if secs > 0 => secs,
+        _ => return,
+    };
+
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            let pool = pool.clone();
+            let result = actix_web::web::block(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                collect_orphans(&mut conn, &GcOptions::from_env()).map_err(|e| e.to_string())
+            })
+            .await;
+
+            match result {
+                Ok(Ok(report)) => log_report(&report),
+                Ok(Err(e)) => eprintln!("Media GC failed: {}", e),
+                Err(e) => eprintln!("Media GC task failed: {}", e),
+            }
+        }
+    });
+}
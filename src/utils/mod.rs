@@ -1,4 +1,19 @@
 pub mod file_upload;
 pub mod validation;
 pub mod auth;
-pub mod img_upload;
\ No newline at end of file
+pub mod img_upload;
+pub mod upload;
+pub mod multipart_form;
+pub mod media_gc;
+pub mod post_access;
+pub mod post_scheduler;
+pub mod soft_delete;
+pub mod entities;
+pub mod notifications;
+pub mod stream_hub;
+pub mod roles;
+pub mod blocks;
+pub mod account_status;
+pub mod audit;
+pub mod password;
+pub mod password_policy;
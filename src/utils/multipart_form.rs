@@ -0,0 +1,161 @@
+use actix_web::{FromRequest, HttpRequest, dev::Payload};
+use futures_util::TryStreamExt as _;
+use futures_util::future::LocalBoxFuture;
+use serde::Deserialize;
+use serde::de::{DeserializeOwned, Deserializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::utils::{
+    upload::{
+        ImageKind, LimitedMultipart, MAX_IMAGE_SIZE, TempUpload, UploadError, read_text_field,
+        stream_to_temp,
+    },
+    validation::Validator,
+};
+
+/// Limits for one file part of a form.
+pub struct FileLimits {
+    pub name: &'static str,
+    pub max_size: usize,
+    pub max_count: usize,
+    /// Content the file must turn out to be, whatever the client declared.
+    pub accept: &'static [ImageKind],
+}
+
+impl FileLimits {
+    /// Up to `max_count` jpeg, png or webp images of at most `MAX_IMAGE_SIZE`.
+    pub const fn images(name: &'static str, max_count: usize) -> Self {
+        FileLimits {
+            name,
+            max_size: MAX_IMAGE_SIZE,
+            max_count,
+            accept: &[ImageKind::Jpeg, ImageKind::Png, ImageKind::Webp],
+        }
+    }
+}
+
+/// A struct filled from the text parts of a multipart form. Every other part
+/// must be one of `FILES`.
+pub trait MultipartFields: DeserializeOwned {
+    const FILES: &'static [FileLimits];
+}
+
+/// File parts of a form, staged in `TMP_DIR` and grouped by field name.
+#[derive(Default)]
+pub struct Files(HashMap<&'static str, Vec<TempUpload>>);
+
+impl Files {
+    /// Every file sent under `name`, in the order received.
+    pub fn take(&mut self, name: &str) -> Vec<TempUpload> {
+        self.0.remove(name).unwrap_or_default()
+    }
+
+    /// The file sent under `name`, for fields limited to one.
+    pub fn take_one(&mut self, name: &str) -> Option<TempUpload> {
+        self.take(name).pop()
+    }
+}
+
+/// Multipart body read into `T` plus its files. The whole body is bounded by
+/// `MultipartLimit`, each file by its `FileLimits`; anything else is refused
+/// before the handler runs.
+pub struct MultipartForm<T> {
+    pub fields: T,
+    pub files: Files,
+}
+
+impl<T: MultipartFields + 'static> FromRequest for MultipartForm<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let multipart = LimitedMultipart::from_request(req, payload).into_inner();
+        Box::pin(async move {
+            let multipart = multipart?.into_inner();
+            read_form(multipart).await.map_err(|e| {
+                actix_web::error::InternalError::from_response(
+                    "invalid multipart form",
+                    e.to_response(),
+                )
+                .into()
+            })
+        })
+    }
+}
+
+async fn read_form<T: MultipartFields>(
+    mut multipart: actix_multipart::Multipart,
+) -> Result<MultipartForm<T>, UploadError> {
+    let mut text = Map::new();
+    let mut files = Files::default();
+
+    while let Some(mut field) = multipart.try_next().await? {
+        let name = field.name().to_string();
+
+        if let Some(limits) = T::FILES.iter().find(|limits| limits.name == name) {
+            let count = files.0.get(limits.name).map_or(0, Vec::len);
+            if count >= limits.max_count {
+                return Err(UploadError::Invalid(format!(
+                    "At most {} file(s) allowed for '{}'",
+                    limits.max_count, limits.name
+                )));
+            }
+
+            let filename = field
+                .content_disposition()
+                .get_filename()
+                .unwrap_or("unknown.jpg")
+                .to_string();
+            Validator::validate_image_type(&filename).map_err(UploadError::Invalid)?;
+
+            let upload = stream_to_temp(&mut field, filename, limits.max_size).await?;
+            if !upload
+                .kind
+                .is_some_and(|kind| limits.accept.contains(&kind))
+            {
+                let allowed: Vec<&str> = limits.accept.iter().map(ImageKind::extension).collect();
+                return Err(UploadError::Invalid(format!(
+                    "File content for '{}' must be {}",
+                    limits.name,
+                    allowed.join(", ")
+                )));
+            }
+            files.0.entry(limits.name).or_default().push(upload);
+        } else if field.content_disposition().get_filename().is_some() {
+            return Err(UploadError::Invalid(format!(
+                "Unexpected file field '{}'",
+                name
+            )));
+        } else {
+            let value = Value::String(read_text_field(&mut field).await?);
+            // A repeated field becomes a list; see `one_or_many`
+            match text.get_mut(&name) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(first) => *first = Value::Array(vec![first.take(), value]),
+                None => {
+                    text.insert(name, value);
+                }
+            }
+        }
+    }
+
+    let fields = serde_json::from_value(Value::Object(text))
+        .map_err(|e| UploadError::Invalid(format!("Invalid form fields: {}", e)))?;
+    Ok(MultipartForm { fields, files })
+}
+
+/// For fields that may be sent several times: one value or many, as a list.
+pub fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
@@ -0,0 +1,141 @@
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::{
+    models::user::{
+        NOTIFICATION_FOLLOW, NOTIFICATION_MENTION, NewNotification, Notification,
+        NotificationPreference,
+    },
+    schema::{notification_preferences, notifications},
+    utils::blocks::{blocked_between, has_muted},
+    utils::stream_hub::{Audience, STREAM_NOTIFICATION, StreamEvent, emit},
+};
+
+/// Something that happened in a handler that users may want to hear about.
+pub enum DomainEvent {
+    Followed {
+        follower_id: i64,
+        followee_id: i64,
+    },
+    Mentioned {
+        actor_id: i64,
+        user_id: i64,
+        post_id: i32,
+    },
+}
+
+impl DomainEvent {
+    fn into_notification(self) -> NewNotification {
+        match self {
+            DomainEvent::Followed {
+                follower_id,
+                followee_id,
+            } => NewNotification {
+                user_id: followee_id,
+                actor_id: Some(follower_id),
+                kind: NOTIFICATION_FOLLOW.to_string(),
+                post_id: None,
+            },
+            DomainEvent::Mentioned {
+                actor_id,
+                user_id,
+                post_id,
+            } => NewNotification {
+                user_id,
+                actor_id: Some(actor_id),
+                kind: NOTIFICATION_MENTION.to_string(),
+                post_id: Some(post_id),
+            },
+        }
+    }
+}
+
+/// A way of getting a notification to its recipient. Email or push go in as
+/// further implementations listed in `deliveries`.
+pub trait Delivery {
+    /// Whether the recipient wants this channel for the notification's kind.
+    fn enabled(&self, preference: &NotificationPreference) -> bool;
+    fn deliver(&self, conn: &mut PgConnection, notification: &NewNotification) -> QueryResult<()>;
+}
+
+/// Stores the notification for the in-app list and pushes it to open streams.
+pub struct InAppDelivery;
+
+impl Delivery for InAppDelivery {
+    fn enabled(&self, preference: &NotificationPreference) -> bool {
+        preference.in_app
+    }
+
+    fn deliver(&self, conn: &mut PgConnection, notification: &NewNotification) -> QueryResult<()> {
+        let stored = diesel::insert_into(notifications::table)
+            .values(notification)
+            .get_result::<Notification>(conn)?;
+        emit(
+            conn,
+            StreamEvent {
+                event: STREAM_NOTIFICATION.to_string(),
+                id: stored.id,
+                audience: Audience::Users(vec![stored.user_id]),
+                data: serde_json::to_value(&stored).unwrap_or_default(),
+            },
+        );
+        Ok(())
+    }
+}
+
+fn deliveries() -> Vec<Box<dyn Delivery>> {
+    vec![Box::new(InAppDelivery)]
+}
+
+/// The user's settings for `kind`, falling back to the defaults when unset.
+pub fn preference_for(
+    conn: &mut PgConnection,
+    user_id: i64,
+    kind: &str,
+) -> QueryResult<NotificationPreference> {
+    let stored = notification_preferences::table
+        .filter(notification_preferences::user_id.eq(user_id))
+        .filter(notification_preferences::kind.eq(kind))
+        .first::<NotificationPreference>(conn)
+        .optional()?;
+
+    Ok(stored.unwrap_or_else(|| NotificationPreference {
+        user_id,
+        kind: kind.to_string(),
+        in_app: true,
+        email: false,
+        push: false,
+    }))
+}
+
+/// Turn an event into a notification and hand it to every channel the recipient enabled.
+/// Nobody is notified about their own actions, or by users they blocked, were
+/// blocked by or muted.
+pub fn publish(conn: &mut PgConnection, event: DomainEvent) -> QueryResult<()> {
+    let notification = event.into_notification();
+    if let Some(actor_id) = notification.actor_id
+        && (actor_id == notification.user_id
+            || blocked_between(conn, notification.user_id, &[actor_id])?
+            || has_muted(conn, notification.user_id, actor_id)?)
+    {
+        return Ok(());
+    }
+
+    let preference = preference_for(conn, notification.user_id, &notification.kind)?;
+    for delivery in deliveries() {
+        if delivery.enabled(&preference) {
+            delivery.deliver(conn, &notification)?;
+        }
+    }
+    Ok(())
+}
+
+/// Publish events after the action they describe has committed. Failures are
+/// logged and never undo the action itself.
+pub fn publish_all(conn: &mut PgConnection, events: Vec<DomainEvent>) {
+    for event in events {
+        if let Err(e) = publish(conn, event) {
+            eprintln!("Notification delivery failed: {}", e);
+        }
+    }
+}
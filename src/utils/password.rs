@@ -0,0 +1,253 @@
+use actix_web::HttpResponse;
+use argon2::password_hash::{
+    PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString, rand_core::OsRng,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::Utc;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::sync::OnceLock;
+
+use crate::{
+    schema::users,
+    utils::password_policy::PasswordIssue,
+    utils::soft_delete::live_users,
+    utils::validation::{Validator, add_password_issues, validation_response},
+};
+use validator::ValidationErrors;
+
+/// One way of turning passwords into stored hashes and checking them again.
+pub trait PasswordHasher: Send + Sync {
+    fn hash(&self, password: &str) -> Result<String, String>;
+    fn verify(&self, password: &str, hash: &str) -> bool;
+    /// Whether `hash` was produced by this hasher (any parameters).
+    fn recognizes(&self, hash: &str) -> bool;
+    /// Whether `hash` should be replaced with a fresh one from this hasher.
+    fn needs_rehash(&self, hash: &str) -> bool;
+}
+
+/// Argon2id, tuned through `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and
+/// `ARGON2_PARALLELISM`. Defaults follow the OWASP recommendation.
+pub struct Argon2idHasher {
+    params: Params,
+}
+
+impl Argon2idHasher {
+    pub fn from_env() -> Self {
+        let setting = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(default)
+        };
+        let params = Params::new(
+            setting("ARGON2_MEMORY_KIB", 19 * 1024),
+            setting("ARGON2_ITERATIONS", 2),
+            setting("ARGON2_PARALLELISM", 1),
+            None,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid Argon2 settings ({}), using defaults", e);
+            Params::default()
+        });
+        Argon2idHasher { params }
+    }
+
+    fn argon2(&self) -> Argon2<'_> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl PasswordHasher for Argon2idHasher {
+    fn hash(&self, password: &str) -> Result<String, String> {
+        let salt = SaltString::generate(&mut OsRng);
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> bool {
+        // Parameters come from the stored hash, so older settings still verify
+        PasswordHash::new(hash).is_ok_and(|parsed| {
+            self.argon2()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2")
+    }
+
+    fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+        parsed.algorithm != Algorithm::Argon2id.ident()
+            || Params::try_from(&parsed).map_or(true, |params| {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            })
+    }
+}
+
+/// Hashes written before Argon2id; only kept for verifying.
+pub struct BcryptHasher;
+
+impl PasswordHasher for BcryptHasher {
+    fn hash(&self, password: &str) -> Result<String, String> {
+        bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|e| e.to_string())
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> bool {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$2")
+    }
+
+    fn needs_rehash(&self, _hash: &str) -> bool {
+        true
+    }
+}
+
+/// The hasher new passwords go through, followed by the ones still accepted
+/// for existing hashes.
+struct Hashers {
+    current: Box<dyn PasswordHasher>,
+    legacy: Vec<Box<dyn PasswordHasher>>,
+}
+
+fn hashers() -> &'static Hashers {
+    static HASHERS: OnceLock<Hashers> = OnceLock::new();
+    HASHERS.get_or_init(|| Hashers {
+        current: Box::new(Argon2idHasher::from_env()),
+        legacy: vec![Box::new(BcryptHasher)],
+    })
+}
+
+/// Hash a password with the current algorithm.
+pub fn hash_password(password: &str) -> Result<String, String> {
+    hashers().current.hash(password)
+}
+
+/// Check a password against a stored hash from any supported algorithm.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    let hashers = hashers();
+    std::iter::once(&hashers.current)
+        .chain(hashers.legacy.iter())
+        .find(|hasher| hasher.recognizes(hash))
+        .is_some_and(|hasher| hasher.verify(password, hash))
+}
+
+/// Whether a stored hash is from an older algorithm or older parameters.
+pub fn needs_rehash(hash: &str) -> bool {
+    let current = &hashers().current;
+    !current.recognizes(hash) || current.needs_rehash(hash)
+}
+
+pub enum PasswordError {
+    /// Policy failures, reported under the request field that held the password.
+    Invalid {
+        field: &'static str,
+        issues: Vec<PasswordIssue>,
+    },
+    NotFound,
+    Hash(String),
+    Database(diesel::result::Error),
+}
+
+impl PasswordError {
+    /// Report policy failures under `field` instead of `password`.
+    pub fn for_field(self, field: &'static str) -> Self {
+        match self {
+            PasswordError::Invalid { issues, .. } => PasswordError::Invalid { field, issues },
+            other => other,
+        }
+    }
+
+    pub fn to_response(&self) -> HttpResponse {
+        match self {
+            PasswordError::Invalid { field, issues } => {
+                let mut errors = ValidationErrors::new();
+                add_password_issues(&mut errors, field, issues.clone());
+                validation_response(&errors)
+            }
+            PasswordError::NotFound => HttpResponse::NotFound()
+                .json(serde_json::json!({ "status": false, "message": "User not found" })),
+            PasswordError::Hash(msg) => {
+                eprintln!("Failed to hash password: {}", msg);
+                HttpResponse::InternalServerError().json(
+                    serde_json::json!({ "status": false, "message": "Failed to process password" }),
+                )
+            }
+            PasswordError::Database(e) => {
+                eprintln!("Database update failed: {}", e);
+                HttpResponse::InternalServerError().json(serde_json::json!({
+                    "status": false,
+                    "message": "Failed to update password",
+                    "error": e.to_string()
+                }))
+            }
+        }
+    }
+}
+
+impl From<diesel::result::Error> for PasswordError {
+    fn from(e: diesel::result::Error) -> Self {
+        PasswordError::Database(e)
+    }
+}
+
+/// Validate a new password and hash it. The only way a plain password
+/// becomes something storable. `user_inputs` are the account's own details.
+pub fn hash_new_password(password: &str, user_inputs: &[&str]) -> Result<String, PasswordError> {
+    Validator::validate_password(password, user_inputs).map_err(|issues| {
+        PasswordError::Invalid {
+            field: "password",
+            issues,
+        }
+    })?;
+    hash_password(password).map_err(PasswordError::Hash)
+}
+
+/// Replace a live user's password and revoke the tokens issued before it.
+/// Used by every path that sets one (change, reset, admin tools); callers
+/// check who is allowed to.
+pub fn set_password(
+    conn: &mut PgConnection,
+    user_id: i64,
+    password: &str,
+) -> Result<(), PasswordError> {
+    let (email, firstname, lastname, username) = live_users()
+        .filter(users::id.eq(user_id))
+        .select((
+            users::email,
+            users::firstname,
+            users::lastname,
+            users::username,
+        ))
+        .first::<(String, String, String, Option<String>)>(conn)
+        .optional()
+        .map_err(PasswordError::Database)?
+        .ok_or(PasswordError::NotFound)?;
+    let user_inputs = [
+        email.as_str(),
+        firstname.as_str(),
+        lastname.as_str(),
+        username.as_deref().unwrap_or_default(),
+    ];
+
+    let hashed = hash_new_password(password, &user_inputs)?;
+    diesel::update(users::table.filter(users::id.eq(user_id)))
+        .set((
+            users::password.eq(hashed),
+            users::password_changed_at.eq(Some(Utc::now().naive_utc())),
+        ))
+        .execute(conn)
+        .map_err(PasswordError::Database)?;
+    Ok(())
+}
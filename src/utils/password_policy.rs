@@ -0,0 +1,467 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Most common leaked passwords, always checked.
+const BUNDLED_BREACHED: &str = include_str!("breached_passwords.txt");
+
+/// Shortest run that counts as a sequence or repeat.
+const MIN_PATTERN: usize = 3;
+/// Shortest known word or personal detail looked for.
+const MIN_WORD: usize = 4;
+/// Rough guesses needed to hit a known word, in bits.
+const WORD_BITS: f64 = 10.0;
+/// The account's own details are among the first things tried.
+const PERSONAL_BITS: f64 = 4.0;
+
+/// Why a password was refused. `code` is stable for the frontend,
+/// `message` is ready to show.
+#[derive(Serialize, Clone)]
+pub struct PasswordIssue {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl PasswordIssue {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        PasswordIssue {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// What a new password must satisfy. Read from the environment once:
+/// `PASSWORD_MIN_LENGTH` (8), `PASSWORD_MAX_LENGTH` (128),
+/// `PASSWORD_REQUIRE_UPPERCASE`, `_LOWERCASE`, `_DIGIT`, `_SYMBOL` (all off),
+/// `PASSWORD_MIN_SCORE` (0-4, default 2), `PASSWORD_CHECK_BREACHED` (on) and
+/// `BREACHED_PASSWORDS_FILE` for an extra list, one password per line.
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub min_score: u8,
+    pub check_breached: bool,
+    breached: HashSet<String>,
+    /// Length of the longest breached entry, so word lookups stay bounded.
+    longest_breached: usize,
+}
+
+fn env_flag(name: &str, default: bool) -> bool {
+    match std::env::var(name).as_deref() {
+        Ok("true") | Ok("1") => true,
+        Ok("false") | Ok("0") => false,
+        _ => default,
+    }
+}
+
+fn env_number<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+fn load_breached() -> HashSet<String> {
+    let mut breached: HashSet<String> = BUNDLED_BREACHED
+        .lines()
+        .map(|line| line.trim().to_lowercase())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if let Ok(path) = std::env::var("BREACHED_PASSWORDS_FILE") {
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => breached.extend(
+                contents
+                    .lines()
+                    .map(|line| line.trim().to_lowercase())
+                    .filter(|line| !line.is_empty()),
+            ),
+            Err(e) => eprintln!("Failed to read breached password list {}: {}", path, e),
+        }
+    }
+    breached
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Self {
+        let breached = load_breached();
+        PasswordPolicy {
+            min_length: env_number("PASSWORD_MIN_LENGTH", 8),
+            max_length: env_number("PASSWORD_MAX_LENGTH", 128),
+            require_uppercase: env_flag("PASSWORD_REQUIRE_UPPERCASE", false),
+            require_lowercase: env_flag("PASSWORD_REQUIRE_LOWERCASE", false),
+            require_digit: env_flag("PASSWORD_REQUIRE_DIGIT", false),
+            require_symbol: env_flag("PASSWORD_REQUIRE_SYMBOL", false),
+            min_score: env_number("PASSWORD_MIN_SCORE", 2).min(4),
+            check_breached: env_flag("PASSWORD_CHECK_BREACHED", true),
+            longest_breached: breached
+                .iter()
+                .map(|w| w.chars().count())
+                .max()
+                .unwrap_or(0),
+            breached,
+        }
+    }
+
+    /// Every rule `password` breaks. `user_inputs` are the account's own
+    /// details (email, names), which make a password easier to guess.
+    pub fn check(&self, password: &str, user_inputs: &[&str]) -> Result<(), Vec<PasswordIssue>> {
+        if password.is_empty() {
+            return Err(vec![PasswordIssue::new("required", "Password is required")]);
+        }
+
+        let mut issues = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            issues.push(PasswordIssue::new(
+                "too_short",
+                format!("Password must be at least {} characters", self.min_length),
+            ));
+        }
+        if length > self.max_length {
+            // Nothing else is worth scoring at this size
+            issues.push(PasswordIssue::new(
+                "too_long",
+                format!("Password must be at most {} characters", self.max_length),
+            ));
+            return Err(issues);
+        }
+        // Login trims what it is given, so a password must not start or end with spaces
+        if password.trim() != password {
+            issues.push(PasswordIssue::new(
+                "surrounding_whitespace",
+                "Password can't start or end with a space",
+            ));
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            issues.push(PasswordIssue::new(
+                "missing_uppercase",
+                "Password must have at least 1 uppercase letter",
+            ));
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            issues.push(PasswordIssue::new(
+                "missing_lowercase",
+                "Password must have at least 1 lowercase letter",
+            ));
+        }
+        if self.require_digit && !password.chars().any(char::is_numeric) {
+            issues.push(PasswordIssue::new(
+                "missing_digit",
+                "Password must have at least 1 number",
+            ));
+        }
+        if self.require_symbol && !password.chars().any(is_symbol) {
+            issues.push(PasswordIssue::new(
+                "missing_symbol",
+                "Password must have at least 1 special character",
+            ));
+        }
+
+        if self.check_breached && self.breached.contains(&password.to_lowercase()) {
+            issues.push(PasswordIssue::new(
+                "breached",
+                "This password has appeared in a data breach; choose another",
+            ));
+        }
+        let score = self.strength(password, user_inputs);
+        if score < self.min_score {
+            issues.push(PasswordIssue::new(
+                "too_weak",
+                format!(
+                    "Password is too easy to guess (strength {} of 4, needs {}); \
+                     try a longer phrase without common words or patterns",
+                    score, self.min_score
+                ),
+            ));
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// A zxcvbn-style score from 0 (trivial) to 4 (strong). Known words,
+    /// the user's own details, repeats, sequences and keyboard runs count
+    /// for little; everything else by the size of its character class.
+    pub fn strength(&self, password: &str, user_inputs: &[&str]) -> u8 {
+        score(self.estimate_bits(password, user_inputs))
+    }
+
+    fn estimate_bits(&self, password: &str, user_inputs: &[&str]) -> f64 {
+        let chars: Vec<char> = password.chars().collect();
+        let lower: Vec<char> = chars
+            .iter()
+            .map(|c| c.to_lowercase().next().unwrap_or(*c))
+            .collect();
+        let plain: Vec<char> = chars.iter().map(|c| unleet(*c)).collect();
+        let personal: Vec<String> = user_inputs
+            .iter()
+            .flat_map(|input| input.split(|c: char| !c.is_alphanumeric()))
+            .map(str::to_lowercase)
+            .filter(|part| part.chars().count() >= MIN_WORD)
+            .collect();
+
+        let mut bits = 0.0;
+        let mut i = 0;
+        while i < chars.len() {
+            let word = [
+                self.word_at(&lower, i, &personal),
+                self.word_at(&plain, i, &personal),
+            ]
+            .into_iter()
+            .flatten()
+            .max_by_key(|(len, _)| *len);
+            let run = pattern_run(&chars, i);
+            // Whichever explains more of the password is how it would be guessed
+            if let Some((len, word_bits)) = word
+                && (len > run || run < MIN_PATTERN)
+            {
+                bits += word_bits;
+                i += len;
+                continue;
+            }
+            if run >= MIN_PATTERN {
+                // The first character and the kind of run are all that needs guessing
+                bits += class_bits(chars[i]) + 2.0;
+                i += run;
+                continue;
+            }
+            bits += class_bits(chars[i]);
+            i += 1;
+        }
+        bits
+    }
+
+    /// Length of the longest known word or personal detail starting at `start`.
+    /// Returns the match length and what guessing it is worth.
+    fn word_at(&self, chars: &[char], start: usize, personal: &[String]) -> Option<(usize, f64)> {
+        let longest = personal
+            .iter()
+            .map(|p| p.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(self.longest_breached);
+        let last = chars.len().min(start + longest);
+        (start + MIN_WORD..=last).rev().find_map(|end| {
+            let candidate: String = chars[start..end].iter().collect();
+            if personal.contains(&candidate) {
+                Some((end - start, PERSONAL_BITS))
+            } else if self.breached.contains(&candidate) {
+                Some((end - start, WORD_BITS))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Score band for an estimate of the guesses needed, in bits.
+fn score(bits: f64) -> u8 {
+    match bits {
+        b if b < 10.0 => 0,
+        b if b < 20.0 => 1,
+        b if b < 27.0 => 2,
+        b if b < 33.0 => 3,
+        _ => 4,
+    }
+}
+
+fn is_symbol(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+/// Undo common character swaps so `p@ssw0rd` reads as `password`.
+fn unleet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        c => c.to_lowercase().next().unwrap_or(c),
+    }
+}
+
+/// Bits for guessing one character out of its class.
+fn class_bits(c: char) -> f64 {
+    let class_size: f64 = if c.is_ascii_lowercase() || c.is_ascii_uppercase() {
+        26.0
+    } else if c.is_ascii_digit() {
+        10.0
+    } else if c.is_ascii() {
+        33.0
+    } else {
+        100.0
+    };
+    class_size.log2()
+}
+
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// Characters from `start` that repeat, count up or down, or walk a keyboard row.
+fn pattern_run(chars: &[char], start: usize) -> usize {
+    let step = |a: char, b: char| -> bool {
+        let (a, b) = (a.to_ascii_lowercase(), b.to_ascii_lowercase());
+        a == b
+            || (b as i64 - a as i64).abs() == 1
+            || KEYBOARD_ROWS.iter().any(|row| {
+                let a_at = row.find(a);
+                let b_at = row.find(b);
+                matches!((a_at, b_at), (Some(x), Some(y)) if x.abs_diff(y) == 1)
+            })
+    };
+
+    let mut len = 1;
+    while start + len < chars.len() && step(chars[start + len - 1], chars[start + len]) {
+        len += 1;
+    }
+    len
+}
+
+/// The policy new passwords are checked against.
+pub fn policy() -> &'static PasswordPolicy {
+    static POLICY: OnceLock<PasswordPolicy> = OnceLock::new();
+    POLICY.get_or_init(PasswordPolicy::from_env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default policy with only the bundled breached list.
+    fn default_policy() -> PasswordPolicy {
+        let breached: HashSet<String> = BUNDLED_BREACHED
+            .lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|line| !line.is_empty())
+            .collect();
+        PasswordPolicy {
+            min_length: 8,
+            max_length: 128,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_symbol: false,
+            min_score: 2,
+            check_breached: true,
+            longest_breached: breached
+                .iter()
+                .map(|w| w.chars().count())
+                .max()
+                .unwrap_or(0),
+            breached,
+        }
+    }
+
+    fn codes(result: Result<(), Vec<PasswordIssue>>) -> Vec<&'static str> {
+        result
+            .err()
+            .unwrap_or_default()
+            .iter()
+            .map(|issue| issue.code)
+            .collect()
+    }
+
+    #[test]
+    fn score_bands() {
+        assert_eq!(score(0.0), 0);
+        assert_eq!(score(9.9), 0);
+        assert_eq!(score(10.0), 1);
+        assert_eq!(score(19.9), 1);
+        assert_eq!(score(20.0), 2);
+        assert_eq!(score(26.9), 2);
+        assert_eq!(score(27.0), 3);
+        assert_eq!(score(32.9), 3);
+        assert_eq!(score(33.0), 4);
+    }
+
+    #[test]
+    fn patterns_and_known_words_score_low() {
+        let policy = default_policy();
+        assert!(policy.strength("password", &[]) <= 1);
+        assert_eq!(policy.strength("aaaaaaaaaaaa", &[]), 0);
+        assert_eq!(policy.strength("abcdefghijkl", &[]), 0);
+        assert_eq!(policy.strength("asdfghjkl", &[]), 0);
+        assert!(policy.strength("dragonmonkey", &[]) < policy.strength("xkqvbzrtwmpl", &[]));
+    }
+
+    #[test]
+    fn random_passwords_score_high() {
+        let policy = default_policy();
+        assert_eq!(policy.strength("vX9#qL2!mR7$kT4@", &[]), 4);
+        assert!(policy.strength("gravel orbit tulip", &[]) >= 3);
+    }
+
+    #[test]
+    fn leet_substitutions_are_folded() {
+        let policy = default_policy();
+        assert_eq!(
+            policy.strength("dr4g0n", &[]),
+            policy.strength("dragon", &[])
+        );
+        assert!(policy.strength("p4$$w0rd", &[]) <= 1);
+    }
+
+    #[test]
+    fn personal_details_score_lower() {
+        let policy = default_policy();
+        let inputs = ["jonathan.quill@example.com", "Jonathan", "Quill"];
+        assert!(policy.strength("jonathanquill", &inputs) < policy.strength("jonathanquill", &[]));
+        assert!(policy.strength("Quill7Jonathan", &inputs) <= 1);
+        assert_eq!(
+            codes(policy.check("jonathanquill", &inputs)),
+            vec!["too_weak"]
+        );
+    }
+
+    #[test]
+    fn breached_passwords_are_refused() {
+        let policy = default_policy();
+        assert!(codes(policy.check("Sunshine", &[])).contains(&"breached"));
+        assert!(codes(policy.check("P@ssw0rd", &[])).contains(&"breached"));
+
+        let unchecked = PasswordPolicy {
+            check_breached: false,
+            ..default_policy()
+        };
+        assert!(!codes(unchecked.check("Sunshine", &[])).contains(&"breached"));
+    }
+
+    #[test]
+    fn issue_codes() {
+        let policy = default_policy();
+        assert_eq!(codes(policy.check("", &[])), vec!["required"]);
+        assert!(codes(policy.check("x9#L", &[])).contains(&"too_short"));
+        assert_eq!(codes(policy.check(&"q".repeat(129), &[])), vec!["too_long"]);
+        assert!(codes(policy.check(" vX9#qL2!mR7$kT4@", &[])).contains(&"surrounding_whitespace"));
+        assert!(codes(policy.check("vX9#qL2!mR7$kT4@ ", &[])).contains(&"surrounding_whitespace"));
+        assert_eq!(
+            codes(policy.check("vX9#qL2!mR7$kT4@", &[])),
+            Vec::<&str>::new()
+        );
+
+        let strict = PasswordPolicy {
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..default_policy()
+        };
+        assert_eq!(
+            codes(strict.check("gravel orbit tulip", &[])),
+            vec!["missing_uppercase", "missing_digit", "missing_symbol"]
+        );
+        assert_eq!(
+            codes(strict.check("GRAVEL ORBIT TULIP 7!", &[])),
+            vec!["missing_lowercase"]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use diesel::dsl::{Eq, InnerJoinOn, InnerJoinQuerySource, IntoBoxed, not};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+
+use crate::{
+    models::user::POST_STATUS_PUBLISHED,
+    schema::{follows, posts, users},
+    utils::blocks::{blocked_by, blockers_of, muted_by},
+};
+
+pub type PostListing<'a> =
+    IntoBoxed<'a, InnerJoinOn<posts::table, users::table, Eq<posts::userid, users::id>>, Pg>;
+
+type PostSource = InnerJoinQuerySource<posts::table, users::table, Eq<posts::userid, users::id>>;
+
+pub type PostFilter = Box<dyn BoxableExpression<PostSource, Pg, SqlType = Bool>>;
+
+/// Live posts joined with their live authors; callers add filters, ordering and paging.
+/// Use `listable_by` or `viewable_by` so visibility is always enforced.
+pub fn post_listing<'a>() -> PostListing<'a> {
+    posts::table
+        .inner_join(users::table.on(posts::userid.eq(users::id)))
+        .filter(posts::deleted_at.is_null())
+        .filter(users::deleted_at.is_null())
+        .into_boxed()
+}
+
+/// Published posts of authors followed by `viewer_id` with `followers` visibility.
+fn followers_only_visible(viewer_id: i64) -> PostFilter {
+    let followed = follows::table
+        .filter(follows::follower_id.eq(viewer_id))
+        .select(follows::followee_id);
+    Box::new(
+        posts::visibility
+            .eq("followers")
+            .and(posts::userid.eq_any(followed)),
+    )
+}
+
+/// Authors on either side of a block with `viewer_id` disappear for them.
+fn author_not_blocked(viewer_id: i64) -> PostFilter {
+    Box::new(
+        not(posts::userid.eq_any(blocked_by(viewer_id)))
+            .and(not(posts::userid.eq_any(blockers_of(viewer_id)))),
+    )
+}
+
+/// Posts hidden by a moderator stay visible to their author only.
+fn not_hidden_from(viewer_id: i64) -> PostFilter {
+    Box::new(posts::hidden_at.is_null().or(posts::userid.eq(viewer_id)))
+}
+
+/// Posts that may appear in timelines and feeds for `viewer_id`.
+/// Unlisted posts are never listed, drafts only exist for their author,
+/// blocked and muted authors are left out.
+pub fn listable_by(viewer_id: i64) -> PostFilter {
+    Box::new(
+        posts::status
+            .eq(POST_STATUS_PUBLISHED)
+            .and(
+                posts::userid
+                    .eq(viewer_id)
+                    .or(posts::visibility.eq("public"))
+                    .or(followers_only_visible(viewer_id)),
+            )
+            .and(not_hidden_from(viewer_id))
+            .and(author_not_blocked(viewer_id))
+            .and(not(posts::userid.eq_any(muted_by(viewer_id)))),
+    )
+}
+
+/// Posts `viewer_id` may open directly by id. Muting doesn't apply here.
+pub fn viewable_by(viewer_id: i64) -> PostFilter {
+    let published = posts::status
+        .eq(POST_STATUS_PUBLISHED)
+        .and(posts::hidden_at.is_null())
+        .and(
+            posts::visibility
+                .eq_any(["public", "unlisted"])
+                .or(followers_only_visible(viewer_id)),
+        );
+    Box::new(
+        posts::userid
+            .eq(viewer_id)
+            .or(published.and(author_not_blocked(viewer_id))),
+    )
+}
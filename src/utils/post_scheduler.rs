@@ -0,0 +1,71 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::time::Duration;
+
+use crate::{
+    db::Pool,
+    handlers::tag_handler::{mention_events, mentioned_users},
+    models::user::{POST_STATUS_PUBLISHED, POST_STATUS_SCHEDULED},
+    schema::posts,
+    utils::notifications::publish_all,
+    utils::soft_delete::live_posts,
+    utils::stream_hub::{STREAM_POST_CREATED, emit_post},
+};
+
+/// Publish every scheduled post whose `publish_at` has passed.
+/// `published_at` takes the scheduled time so the feed order matches the plan.
+pub fn publish_due_posts(conn: &mut PgConnection) -> QueryResult<usize> {
+    let now = Utc::now().naive_utc();
+    let published = diesel::update(
+        live_posts()
+            .filter(posts::status.eq(POST_STATUS_SCHEDULED))
+            .filter(posts::publish_at.le(now)),
+    )
+    .set((
+        posts::status.eq(POST_STATUS_PUBLISHED),
+        posts::published_at.eq(posts::publish_at),
+        posts::publish_at.eq(None::<NaiveDateTime>),
+    ))
+    .returning((posts::id, posts::userid))
+    .get_results::<(i32, i64)>(conn)?;
+
+    for (post_id, author_id) in &published {
+        let events = mentioned_users(conn, *post_id)
+            .and_then(|ids| mention_events(conn, *post_id, *author_id, &ids));
+        publish_all(conn, events.unwrap_or_default());
+        emit_post(conn, STREAM_POST_CREATED, *post_id);
+    }
+    Ok(published.len())
+}
+
+/// Run the scheduler every `POST_SCHEDULER_INTERVAL_SECS` (default 30s, 0 disables it).
+pub fn spawn_background(pool: Pool) {
+    let interval_secs = std::env::var("POST_SCHEDULER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(30);
+    if interval_secs == 0 {
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            let pool = pool.clone();
+            let result = actix_web::web::block(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                publish_due_posts(&mut conn).map_err(|e| e.to_string())
+            })
+            .await;
+
+            match result {
+                Ok(Ok(0)) => {}
+                Ok(Ok(count)) => println!("Post scheduler: published {} post(s)", count),
+                Ok(Err(e)) => eprintln!("Post scheduler failed: {}", e),
+                Err(e) => eprintln!("Post scheduler task failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,36 @@
+use actix_web::HttpResponse;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::{schema::users, utils::soft_delete::live_users};
+
+/// The role of `user_id` when it is one of `allowed`, otherwise a 403.
+/// Roles are read from the database so changes apply without a new token.
+#[allow(clippy::result_large_err)]
+pub fn require_role(
+    conn: &mut PgConnection,
+    user_id: i64,
+    allowed: &[&str],
+) -> Result<String, HttpResponse> {
+    let role = live_users()
+        .filter(users::id.eq(user_id))
+        .select(users::role)
+        .first::<String>(conn)
+        .optional();
+
+    match role {
+        Ok(Some(role)) if allowed.contains(&role.as_str()) => Ok(role),
+        Ok(_) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "status": false,
+            "message": "You don't have permission to do this"
+        }))),
+        Err(e) => {
+            eprintln!("Database query failed: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "status": false,
+                "message": "Database error",
+                "error": e.to_string()
+            })))
+        }
+    }
+}
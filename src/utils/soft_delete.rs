@@ -0,0 +1,181 @@
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use diesel::dsl::{Filter, IsNull};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::{
+    db::Pool,
+    schema::{messages, post_images, post_revisions, posts, uploads, users},
+    utils::{
+        file_upload::PROFILE_DIR,
+        img_upload::{MESSAGE_DIR, POST_DIR},
+        upload::remove_stored_files,
+    },
+};
+
+pub type LivePosts = Filter<posts::table, IsNull<posts::deleted_at>>;
+pub type LiveUsers = Filter<users::table, IsNull<users::deleted_at>>;
+
+/// Posts that are not in the trash. Start post queries here instead of `posts::table`.
+pub fn live_posts() -> LivePosts {
+    posts::table.filter(posts::deleted_at.is_null())
+}
+
+/// Users that are not in the trash. Start user queries here instead of `users::table`.
+pub fn live_users() -> LiveUsers {
+    users::table.filter(users::deleted_at.is_null())
+}
+
+/// How long trashed rows can be restored, from `TRASH_RETENTION_DAYS` (default 30).
+pub fn retention() -> ChronoDuration {
+    let days = std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(30);
+    ChronoDuration::days(days)
+}
+
+/// When a row trashed at `deleted_at` gets purged.
+pub fn purge_at(deleted_at: NaiveDateTime) -> NaiveDateTime {
+    deleted_at + retention()
+}
+
+/// Whether a row trashed at `deleted_at` can still be restored.
+pub fn restorable(deleted_at: NaiveDateTime) -> bool {
+    purge_at(deleted_at) > Utc::now().naive_utc()
+}
+
+#[derive(Serialize)]
+pub struct PurgeReport {
+    pub posts: usize,
+    pub users: usize,
+    pub files: usize,
+}
+
+/// Storage keys of the current and historical images of the given posts.
+pub fn post_files(conn: &mut PgConnection, post_ids: &[i32]) -> QueryResult<Vec<String>> {
+    let mut keys = post_images::table
+        .filter(post_images::post_id.eq_any(post_ids))
+        .select(post_images::storage_key)
+        .load::<String>(conn)?;
+    let history = post_revisions::table
+        .filter(post_revisions::post_id.eq_any(post_ids))
+        .select(post_revisions::images)
+        .load::<Vec<Option<String>>>(conn)?;
+    keys.extend(history.into_iter().flatten().flatten());
+    keys.sort_unstable();
+    keys.dedup();
+    Ok(keys)
+}
+
+/// Hard-delete the given users and posts, including every post of those users,
+/// then their files. Dependent rows go with them through ON DELETE CASCADE.
+pub fn hard_delete(
+    conn: &mut PgConnection,
+    user_ids: &[i64],
+    post_ids: &[i32],
+) -> QueryResult<PurgeReport> {
+    let profiles = users::table
+        .filter(users::id.eq_any(user_ids))
+        .select(users::profile)
+        .load::<String>(conn)?;
+    let doomed_posts = posts::table
+        .filter(
+            posts::id
+                .eq_any(post_ids)
+                .or(posts::userid.eq_any(user_ids)),
+        )
+        .select(posts::id)
+        .load::<i32>(conn)?;
+    let post_keys = post_files(conn, &doomed_posts)?;
+    let message_keys: Vec<String> = messages::table
+        .filter(messages::sender_id.eq_any(user_ids))
+        .select(messages::images)
+        .load::<Vec<Option<String>>>(conn)?
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect();
+
+    let (posts_deleted, users_deleted) =
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let posts_deleted =
+                diesel::delete(posts::table.filter(posts::id.eq_any(&doomed_posts)))
+                    .execute(conn)?;
+            let users_deleted =
+                diesel::delete(users::table.filter(users::id.eq_any(user_ids))).execute(conn)?;
+            diesel::delete(
+                uploads::table.filter(
+                    uploads::storage_key
+                        .eq_any(&post_keys)
+                        .or(uploads::storage_key.eq_any(&profiles))
+                        .or(uploads::storage_key.eq_any(&message_keys)),
+                ),
+            )
+            .execute(conn)?;
+            Ok((posts_deleted, users_deleted))
+        })?;
+
+    // Files go only after the rows that referenced them are gone
+    remove_stored_files(POST_DIR, &post_keys);
+    remove_stored_files(PROFILE_DIR, &profiles);
+    remove_stored_files(MESSAGE_DIR, &message_keys);
+
+    Ok(PurgeReport {
+        posts: posts_deleted,
+        users: users_deleted,
+        files: post_keys.len() + profiles.len() + message_keys.len(),
+    })
+}
+
+/// Hard-delete posts and users whose retention window has run out.
+pub fn purge_expired(conn: &mut PgConnection) -> QueryResult<PurgeReport> {
+    let cutoff = Utc::now().naive_utc() - retention();
+
+    let expired_users = users::table
+        .filter(users::deleted_at.lt(cutoff))
+        .select(users::id)
+        .load::<i64>(conn)?;
+    let expired_posts = posts::table
+        .filter(posts::deleted_at.lt(cutoff))
+        .select(posts::id)
+        .load::<i32>(conn)?;
+
+    hard_delete(conn, &expired_users, &expired_posts)
+}
+
+/// Run the purge every `TRASH_PURGE_INTERVAL_SECS` (default hourly, 0 disables it).
+pub fn spawn_background(pool: Pool) {
+    let interval_secs = std::env::var("TRASH_PURGE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(60 * 60);
+    if interval_secs == 0 {
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            let pool = pool.clone();
+            let result = actix_web::web::block(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                purge_expired(&mut conn).map_err(|e| e.to_string())
+            })
+            .await;
+
+            match result {
+                Ok(Ok(report)) if report.posts == 0 && report.users == 0 => {}
+                Ok(Ok(report)) => println!(
+                    "Trash purge: {} post(s), {} user(s), {} file(s) removed",
+                    report.posts, report.users, report.files
+                ),
+                Ok(Err(e)) => eprintln!("Trash purge failed: {}", e),
+                Err(e) => eprintln!("Trash purge task failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,203 @@
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::{
+    handlers::post_image_handler::{load_post_images, to_post_data},
+    models::user::{POST_STATUS_PUBLISHED, Post},
+    schema::{follows, posts},
+    utils::blocks::shielded_from,
+};
+
+pub const STREAM_POST_CREATED: &str = "post.created";
+pub const STREAM_POST_UPDATED: &str = "post.updated";
+pub const STREAM_POST_DELETED: &str = "post.deleted";
+pub const STREAM_NOTIFICATION: &str = "notification";
+pub const STREAM_MESSAGE: &str = "message";
+
+/// Postgres channel used to share events between instances.
+const PG_CHANNEL: &str = "stream_events";
+/// NOTIFY payloads must stay under 8000 bytes.
+const PG_PAYLOAD_LIMIT: usize = 7900;
+/// Events a slow subscriber may fall behind before it starts skipping.
+const HUB_CAPACITY: usize = 256;
+
+/// Who gets to see an event.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Audience {
+    Everyone,
+    EveryoneExcept(Vec<i64>),
+    Users(Vec<i64>),
+}
+
+impl Audience {
+    pub fn includes(&self, user_id: i64) -> bool {
+        match self {
+            Audience::Everyone => true,
+            Audience::EveryoneExcept(ids) => !ids.contains(&user_id),
+            Audience::Users(ids) => ids.contains(&user_id),
+        }
+    }
+}
+
+/// One message for `/api/stream`. `data` is left null when the event was too
+/// large to fan out; clients refetch the subject by `id` in that case.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StreamEvent {
+    pub event: String,
+    pub id: i64,
+    pub audience: Audience,
+    pub data: serde_json::Value,
+}
+
+fn hub() -> &'static broadcast::Sender<Arc<StreamEvent>> {
+    static HUB: OnceLock<broadcast::Sender<Arc<StreamEvent>>> = OnceLock::new();
+    HUB.get_or_init(|| broadcast::channel(HUB_CAPACITY).0)
+}
+
+pub fn subscribe() -> broadcast::Receiver<Arc<StreamEvent>> {
+    hub().subscribe()
+}
+
+fn broadcast_local(event: StreamEvent) {
+    // Sending only fails when nobody is listening
+    let _ = hub().send(Arc::new(event));
+}
+
+/// `STREAM_PG_NOTIFY=true` routes events through Postgres so every instance sees them.
+fn pg_fan_out() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        matches!(
+            std::env::var("STREAM_PG_NOTIFY").as_deref(),
+            Ok("true") | Ok("1")
+        )
+    })
+}
+
+/// Hand an event to every subscriber. Call after the change it describes has
+/// committed; failures are logged and never undo the change.
+pub fn emit(conn: &mut PgConnection, mut event: StreamEvent) {
+    if !pg_fan_out() {
+        return broadcast_local(event);
+    }
+
+    let mut payload = serde_json::to_string(&event).unwrap_or_default();
+    if payload.len() > PG_PAYLOAD_LIMIT {
+        event.data = serde_json::Value::Null;
+        payload = serde_json::to_string(&event).unwrap_or_default();
+    }
+    // The listener on each instance, this one included, rebroadcasts it
+    let sent = diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(PG_CHANNEL)
+        .bind::<Text, _>(&payload)
+        .execute(conn);
+    if let Err(e) = sent {
+        eprintln!("Stream fan-out failed, delivering locally: {}", e);
+        broadcast_local(event);
+    }
+}
+
+/// Readers who would find `post` in their timelines: everyone for public
+/// posts, followers for followers-only ones, otherwise just the author.
+/// `was_listed` decides for posts that are no longer published. Users
+/// blocking, blocked by or muting the author are left out.
+fn post_audience(conn: &mut PgConnection, post: &Post, was_listed: bool) -> QueryResult<Audience> {
+    if !was_listed {
+        return Ok(Audience::Users(vec![post.userid]));
+    }
+    let shielded = shielded_from(conn, post.userid)?;
+    match post.visibility.as_str() {
+        "public" if shielded.is_empty() => Ok(Audience::Everyone),
+        "public" => Ok(Audience::EveryoneExcept(shielded)),
+        "followers" => {
+            let mut ids = follows::table
+                .filter(follows::followee_id.eq(post.userid))
+                .filter(follows::follower_id.ne_all(&shielded))
+                .select(follows::follower_id)
+                .load::<i64>(conn)?;
+            ids.push(post.userid);
+            Ok(Audience::Users(ids))
+        }
+        _ => Ok(Audience::Users(vec![post.userid])),
+    }
+}
+
+fn post_event(conn: &mut PgConnection, kind: &str, post_id: i32) -> QueryResult<StreamEvent> {
+    let post = posts::table
+        .filter(posts::id.eq(post_id))
+        .first::<Post>(conn)?;
+
+    if kind == STREAM_POST_DELETED {
+        // Tell whoever could have seen it while it was out
+        let audience = post_audience(conn, &post, post.published_at.is_some())?;
+        return Ok(StreamEvent {
+            event: kind.to_string(),
+            id: post.id.into(),
+            audience,
+            data: serde_json::json!({ "id": post.id, "userid": post.userid }),
+        });
+    }
+
+    let published = post.status == POST_STATUS_PUBLISHED
+        && post.deleted_at.is_none()
+        && post.hidden_at.is_none();
+    let audience = post_audience(conn, &post, published)?;
+    let images = load_post_images(conn, &[post.id])?
+        .remove(&post.id)
+        .unwrap_or_default();
+    Ok(StreamEvent {
+        event: kind.to_string(),
+        id: post.id.into(),
+        audience,
+        data: serde_json::to_value(to_post_data(post, images)).unwrap_or_default(),
+    })
+}
+
+/// Announce a change to a post on the stream.
+pub fn emit_post(conn: &mut PgConnection, kind: &str, post_id: i32) {
+    match post_event(conn, kind, post_id) {
+        Ok(event) => emit(conn, event),
+        Err(e) => eprintln!("Stream event for post {} failed: {}", post_id, e),
+    }
+}
+
+/// Rebroadcast events from other instances when `STREAM_PG_NOTIFY` is on.
+/// Runs on its own thread with a dedicated connection, reconnecting on failure.
+pub fn spawn_listener() {
+    if !pg_fan_out() {
+        return;
+    }
+
+    std::thread::spawn(|| {
+        loop {
+            if let Err(e) = listen() {
+                eprintln!("Stream listener failed: {}", e);
+            }
+            std::thread::sleep(Duration::from_secs(5));
+        }
+    });
+}
+
+fn listen() -> Result<(), String> {
+    let database_url = dotenvy::var("DATABASE_URL").map_err(|e| e.to_string())?;
+    let mut conn = PgConnection::establish(&database_url).map_err(|e| e.to_string())?;
+    diesel::sql_query(format!("LISTEN {}", PG_CHANNEL))
+        .execute(&mut conn)
+        .map_err(|e| e.to_string())?;
+
+    loop {
+        for notification in conn.notifications_iter() {
+            let notification = notification.map_err(|e| e.to_string())?;
+            match serde_json::from_str::<StreamEvent>(&notification.payload) {
+                Ok(event) => broadcast_local(event),
+                Err(e) => eprintln!("Malformed stream event: {}", e),
+            }
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,448 @@
+use actix_multipart::{Field, Multipart, MultipartError};
+use actix_web::{
+    dev::Payload, error::PayloadError, http::header, web, FromRequest, HttpRequest, HttpResponse,
+};
+use futures_util::{
+    future::{ready, Ready},
+    StreamExt, TryStreamExt,
+};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::models::user::NewUpload;
+
+/// Maximum size of a single uploaded image
+pub const MAX_IMAGE_SIZE: usize = 3 * 1024 * 1024;
+
+/// Maximum size of a plain text form field
+pub const MAX_TEXT_FIELD_SIZE: usize = 64 * 1024;
+
+/// Directory where uploads are staged before being moved into storage
+pub const TMP_DIR: &str = "files/tmp";
+
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Global limit for a whole multipart request body.
+/// Register it with `app_data` to override the default.
+#[derive(Clone)]
+pub struct MultipartLimit {
+    pub max_payload_size: usize,
+}
+
+impl Default for MultipartLimit {
+    fn default() -> Self {
+        MultipartLimit {
+            max_payload_size: 20 * 1024 * 1024,
+        }
+    }
+}
+
+pub enum UploadError {
+    TooLarge(String),
+    Invalid(String),
+    Io(String),
+}
+
+impl UploadError {
+    pub fn to_response(&self) -> HttpResponse {
+        match self {
+            UploadError::TooLarge(msg) => HttpResponse::PayloadTooLarge()
+                .json(serde_json::json!({ "status": false, "message": msg })),
+            UploadError::Invalid(msg) => HttpResponse::BadRequest()
+                .json(serde_json::json!({ "status": false, "message": msg })),
+            UploadError::Io(msg) => {
+                eprintln!("Upload failed: {}", msg);
+                HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "status": false, "message": "Failed to store upload" }))
+            }
+        }
+    }
+}
+
+impl From<MultipartError> for UploadError {
+    fn from(err: MultipartError) -> Self {
+        match err {
+            MultipartError::Payload(PayloadError::Overflow) => {
+                UploadError::TooLarge("Request body is too large".to_string())
+            }
+            e => UploadError::Invalid(format!("Malformed multipart body: {}", e)),
+        }
+    }
+}
+
+/// Multipart extractor that enforces `MultipartLimit` on the raw body
+/// while it is being read, before any field is parsed.
+pub struct LimitedMultipart(Multipart);
+
+impl LimitedMultipart {
+    pub fn into_inner(self) -> Multipart {
+        self.0
+    }
+}
+
+impl FromRequest for LimitedMultipart {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let limit = req
+            .app_data::<web::Data<MultipartLimit>>()
+            .map(|l| l.max_payload_size)
+            .unwrap_or_else(|| MultipartLimit::default().max_payload_size);
+
+        let declared = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+
+        if declared.is_some_and(|len| len > limit) {
+            let response = HttpResponse::PayloadTooLarge().json(serde_json::json!({
+                "status": false,
+                "message": "Request body is too large"
+            }));
+            return ready(Err(actix_web::error::InternalError::from_response(
+                "payload too large",
+                response,
+            )
+            .into()));
+        }
+
+        let mut received = 0usize;
+        let stream = payload.take().map(move |chunk| {
+            let chunk = chunk?;
+            received += chunk.len();
+            if received > limit {
+                return Err(PayloadError::Overflow);
+            }
+            Ok(chunk)
+        });
+
+        ready(Ok(LimitedMultipart(Multipart::new(req.headers(), stream))))
+    }
+}
+
+/// Image formats accepted for storage, detected from file content.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageKind {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl ImageKind {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageKind::Jpeg => "jpg",
+            ImageKind::Png => "png",
+            ImageKind::Webp => "webp",
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ImageKind::Jpeg => "image/jpeg",
+            ImageKind::Png => "image/png",
+            ImageKind::Webp => "image/webp",
+        }
+    }
+
+    fn sniff(head: &[u8]) -> Option<ImageKind> {
+        if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageKind::Jpeg)
+        } else if head.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+            Some(ImageKind::Png)
+        } else if head.len() >= 12 && &head[0..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+            Some(ImageKind::Webp)
+        } else {
+            None
+        }
+    }
+
+    /// Parse width and height from the start of the file.
+    fn dimensions(&self, data: &[u8]) -> Option<(i32, i32)> {
+        let be16 = |i: usize| Some(u16::from_be_bytes([*data.get(i)?, *data.get(i + 1)?]) as i32);
+        let le16 = |i: usize| Some(u16::from_le_bytes([*data.get(i)?, *data.get(i + 1)?]) as i32);
+        let le24 = |i: usize| {
+            Some(i32::from_le_bytes([
+                *data.get(i)?,
+                *data.get(i + 1)?,
+                *data.get(i + 2)?,
+                0,
+            ]))
+        };
+
+        match self {
+            ImageKind::Png => {
+                let w = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+                let h = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+                Some((w as i32, h as i32))
+            }
+            ImageKind::Jpeg => {
+                let mut i = 2;
+                while i + 9 < data.len() {
+                    if data[i] != 0xFF {
+                        i += 1;
+                        continue;
+                    }
+                    let marker = data[i + 1];
+                    match marker {
+                        0xFF => i += 1,
+                        0x01 | 0xD0..=0xD8 => i += 2,
+                        0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                            return Some((be16(i + 7)?, be16(i + 5)?));
+                        }
+                        _ => i += 2 + be16(i + 2)? as usize,
+                    }
+                }
+                None
+            }
+            ImageKind::Webp => match data.get(12..16)? {
+                b"VP8 " => Some((le16(26)? & 0x3FFF, le16(28)? & 0x3FFF)),
+                b"VP8L" => {
+                    let b = data.get(21..25)?;
+                    let (b0, b1, b2, b3) = (b[0] as i32, b[1] as i32, b[2] as i32, b[3] as i32);
+                    let w = 1 + (((b1 & 0x3F) << 8) | b0);
+                    let h = 1 + (((b3 & 0x0F) << 10) | (b2 << 2) | ((b1 & 0xC0) >> 6));
+                    Some((w, h))
+                }
+                b"VP8X" => Some((1 + le24(24)?, 1 + le24(27)?)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Width and height of a stored image, if its header can be parsed.
+pub fn image_dimensions(path: &Path) -> Option<(i32, i32)> {
+    use std::io::Read;
+
+    let mut data = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(256 * 1024)
+        .read_to_end(&mut data)
+        .ok()?;
+    ImageKind::sniff(&data)?.dimensions(&data)
+}
+
+/// An uploaded file staged in `TMP_DIR`. The file is removed on drop
+/// unless it has been moved into storage with `persist`.
+pub struct TempUpload {
+    pub path: PathBuf,
+    pub original_name: String,
+    pub size: usize,
+    pub kind: Option<ImageKind>,
+}
+
+impl TempUpload {
+    /// Atomically move the staged image into `dir` under a server generated
+    /// name and return the metadata to record for it.
+    pub fn store_in(self, dir: &str) -> Result<NewUpload, String> {
+        let kind = self.kind.ok_or("Unsupported image content")?;
+        std::fs::create_dir_all(dir).map_err(|_| "Failed to create upload directory")?;
+
+        let storage_key = format!("{}.{}", Uuid::new_v4().simple(), kind.extension());
+        let dest = Path::new(dir).join(&storage_key);
+        std::fs::rename(&self.path, &dest).map_err(|_| "Failed to move uploaded file")?;
+        // The media GC ages files by mtime; start the clock when it is stored,
+        // not when the upload began streaming into TMP_DIR
+        std::fs::File::options()
+            .write(true)
+            .open(&dest)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .map_err(|_| "Failed to store uploaded file")?;
+
+        Ok(NewUpload {
+            storage_key,
+            original_name: self.original_name.clone(),
+            mime: kind.mime().to_string(),
+            bytes: self.size as i64,
+        })
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn temp_path() -> Result<PathBuf, UploadError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| UploadError::Io("Failed to get current time".to_string()))?
+        .as_nanos();
+    let seq = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    Ok(Path::new(TMP_DIR).join(format!("{}_{}_{}.part", std::process::id(), nanos, seq)))
+}
+
+/// Remove files that were already moved into `dir`. Used to undo storage
+/// when the database write they belong to did not commit.
+pub fn remove_stored_files(dir: &str, keys: &[String]) {
+    for key in keys {
+        let path = Path::new(dir).join(key);
+        if path.exists()
+            && let Err(e) = std::fs::remove_file(&path)
+        {
+            eprintln!("Failed to remove stored file {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Stream a file field to a temp file, failing as soon as it exceeds `max_size`.
+pub async fn stream_to_temp(
+    field: &mut Field,
+    original_name: String,
+    max_size: usize,
+) -> Result<TempUpload, UploadError> {
+    tokio::fs::create_dir_all(TMP_DIR)
+        .await
+        .map_err(|e| UploadError::Io(e.to_string()))?;
+
+    let path = temp_path()?;
+    let mut file = tokio::fs::File::create(&path)
+        .await
+        .map_err(|e| UploadError::Io(e.to_string()))?;
+
+    // Owns the temp file from here on so every early return cleans it up
+    let mut upload = TempUpload {
+        path,
+        original_name: sanitize_original_name(&original_name),
+        size: 0,
+        kind: None,
+    };
+    let mut head: Vec<u8> = Vec::with_capacity(12);
+
+    while let Some(chunk) = field.try_next().await? {
+        upload.size += chunk.len();
+        if upload.size > max_size {
+            return Err(UploadError::TooLarge(format!(
+                "File size should be less than {}MB",
+                max_size / (1024 * 1024)
+            )));
+        }
+        if head.len() < 12 {
+            let take = (12 - head.len()).min(chunk.len());
+            head.extend_from_slice(&chunk[..take]);
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| UploadError::Io(e.to_string()))?;
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| UploadError::Io(e.to_string()))?;
+
+    upload.kind = ImageKind::sniff(&head);
+    Ok(upload)
+}
+
+/// Keep only the last path component of a client supplied filename.
+fn sanitize_original_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("").trim();
+    let base: String = base.chars().filter(|c| !c.is_control()).take(255).collect();
+    if base.is_empty() {
+        "unknown".to_string()
+    } else {
+        base
+    }
+}
+
+/// Read a text field into a string, bounded by `MAX_TEXT_FIELD_SIZE`.
+pub async fn read_text_field(field: &mut Field) -> Result<String, UploadError> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.try_next().await? {
+        if data.len() + chunk.len() > MAX_TEXT_FIELD_SIZE {
+            return Err(UploadError::TooLarge(format!(
+                "Field '{}' is too large",
+                field.name()
+            )));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&data).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        data.extend_from_slice(&[0, 0, 0, 13]);
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data
+    }
+
+    fn webp(chunk: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = b"RIFF\0\0\0\0WEBP".to_vec();
+        data.extend_from_slice(chunk);
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn sniff_goes_by_content() {
+        assert_eq!(ImageKind::sniff(&[0xFF, 0xD8, 0xFF, 0xE0]), Some(ImageKind::Jpeg));
+        assert_eq!(ImageKind::sniff(&png(1, 1)), Some(ImageKind::Png));
+        assert_eq!(ImageKind::sniff(&webp(b"VP8 ", &[])), Some(ImageKind::Webp));
+        assert_eq!(ImageKind::sniff(b"GIF89a"), None);
+        assert_eq!(ImageKind::sniff(b"<svg xmlns="), None);
+        assert_eq!(ImageKind::sniff(b"RIFF\0\0\0\0WAVE"), None);
+        assert_eq!(ImageKind::sniff(b"RIFF"), None);
+        assert_eq!(ImageKind::sniff(&[]), None);
+    }
+
+    #[test]
+    fn png_dimensions() {
+        assert_eq!(ImageKind::Png.dimensions(&png(640, 480)), Some((640, 480)));
+        assert_eq!(ImageKind::Png.dimensions(&png(640, 480)[..20]), None);
+    }
+
+    #[test]
+    fn jpeg_dimensions_skip_to_frame_header() {
+        let mut data = vec![0xFF, 0xD8];
+        // An APP0 segment of 16 bytes comes before the frame header
+        data.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        data.extend_from_slice(&[0; 14]);
+        // SOF0: length, precision, height 300, width 400
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0x2C, 0x01, 0x90]);
+        data.extend_from_slice(&[0; 8]);
+        assert_eq!(ImageKind::Jpeg.dimensions(&data), Some((400, 300)));
+
+        // A Huffman table segment (0xC4) is not a frame header
+        data[20..22].copy_from_slice(&[0xFF, 0xC4]);
+        assert_eq!(ImageKind::Jpeg.dimensions(&data), None);
+    }
+
+    #[test]
+    fn webp_dimensions() {
+        // Lossy: frame tag and start code, then 14-bit width and height
+        let lossy = webp(b"VP8 ", &[0, 0, 0, 0x9D, 0x01, 0x2A, 0x80, 0x02, 0xE0, 0x01]);
+        assert_eq!(ImageKind::Webp.dimensions(&lossy), Some((640, 480)));
+
+        // Lossless: signature, then width-1 and height-1 in 14 bits each
+        let (w, h) = (640u32 - 1, 480u32 - 1);
+        let bits = w | (h << 14);
+        let mut lossless_payload = vec![0x2F];
+        lossless_payload.extend_from_slice(&bits.to_le_bytes());
+        let lossless = webp(b"VP8L", &lossless_payload);
+        assert_eq!(ImageKind::Webp.dimensions(&lossless), Some((640, 480)));
+
+        // Extended: flags, then 24-bit canvas width-1 and height-1
+        let extended = webp(b"VP8X", &[0, 0, 0, 0, 0x7F, 0x02, 0x00, 0xDF, 0x01, 0x00]);
+        assert_eq!(ImageKind::Webp.dimensions(&extended), Some((640, 480)));
+
+        assert_eq!(ImageKind::Webp.dimensions(&webp(b"ALPH", &[0; 10])), None);
+    }
+}
@@ -1,4 +1,13 @@
+use actix_web::HttpResponse;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use phonenumber::{Mode, country};
 use regex::Regex;
+use std::collections::BTreeMap;
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::models::user::{POST_STATUS_DRAFT, POST_STATUS_PUBLISHED, POST_VISIBILITIES};
+use crate::utils::password_policy::{PasswordIssue, policy};
 
 pub struct Validator;
 
@@ -22,105 +31,99 @@ impl Validator {
         Ok(())
     }
 
-    /// Validate password
-    pub fn validate_password(password: &str) -> Result<(), String> {
-        if password.is_empty() {
-            return Err("Password is required".to_string());
-        }
-
-        // Check length
-        if password.len() < 6 || password.len() > 20 {
-            return Err("Password must be 6-20 characters".to_string());
-        }
+    /// Validate a new password against the configured policy. `user_inputs`
+    /// are the account's own details, which count against its strength.
+    pub fn validate_password(password: &str, user_inputs: &[&str]) -> Result<(), Vec<PasswordIssue>> {
+        policy().check(password, user_inputs)
+    }
 
-        // Check for spaces
-        if password.contains(' ') {
-            return Err("Password must not contain spaces".to_string());
+    /// Normalize and validate a personal name: NFC, trimmed, inner whitespace
+    /// collapsed. Letters from any script are allowed, joined by single spaces,
+    /// hyphens, apostrophes or periods. Returns the form to store.
+    pub fn normalize_name(name: &str, label: &str) -> Result<String, String> {
+        let name: String = name.nfc().collect();
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return Err(format!("{} is required", label));
         }
 
-        // Check for uppercase
-        if !password.chars().any(|c| c.is_uppercase()) {
-            return Err("Password must have at least 1 uppercase letter".to_string());
+        if name.chars().count() > 50 {
+            return Err(format!("{} must be at most 50 characters", label));
         }
 
-        // Check for lowercase
-        if !password.chars().any(|c| c.is_lowercase()) {
-            return Err("Password must have at least 1 lowercase letter".to_string());
+        let is_separator = |c: char| matches!(c, ' ' | '-' | '\'' | '\u{2019}' | '.');
+        let mut previous: Option<char> = None;
+        for c in name.chars() {
+            let allowed = c.is_alphabetic()
+                || is_combining_mark(c)
+                || (is_separator(c) && previous.is_some_and(|p| !is_separator(p)))
+                || (c == ' ' && previous == Some('.'));
+            if !allowed {
+                return Err(format!(
+                    "{} may only contain letters separated by single spaces, hyphens or apostrophes",
+                    label
+                ));
+            }
+            previous = Some(c);
         }
-
-        // Check for digit
-        if !password.chars().any(|c| c.is_numeric()) {
-            return Err("Password must have at least 1 number".to_string());
+        if !name.chars().next().is_some_and(char::is_alphabetic) {
+            return Err(format!("{} must start with a letter", label));
         }
-
-        // Check for special character
-        let special_chars = "!@#$%^&*()_+-=[]{}; ':\"\\|,.<>/?";
-        if !password.chars().any(|c| special_chars.contains(c)) {
-            return Err("Password must have at least 1 special character".to_string());
+        if name.ends_with([' ', '-', '\'', '\u{2019}']) {
+            return Err(format!("{} must end with a letter", label));
         }
 
-        Ok(())
+        Ok(name)
     }
 
-    /// Validate firstname
-    pub fn validate_firstname(name: &str) -> Result<(), String> {
-        if name.is_empty() {
-            return Err("First name is required".to_string());
-        }
-
-        if name.len() < 3 || name.len() > 30 {
-            return Err("Firstname must be 3-30 characters".to_string());
-        }
-
-        let name_regex = Regex::new(r"^[A-Za-z]+$").unwrap();
-        if !name_regex.is_match(name) {
-            return Err("Firstname must contain only letters".to_string());
-        }
-
-        Ok(())
+    pub fn normalize_firstname(name: &str) -> Result<String, String> {
+        Self::normalize_name(name, "First name")
     }
 
-    /// Validate lastname
-    pub fn validate_lastname(name: &str) -> Result<(), String> {
-        if name.is_empty() {
-            return Err("Last name is required".to_string());
-        }
-
-        if name.len() < 1 || name.len() > 20 {
-            return Err("Lastname must be 1-20 characters".to_string());
-        }
+    pub fn normalize_lastname(name: &str) -> Result<String, String> {
+        Self::normalize_name(name, "Last name")
+    }
 
-        let name_regex = Regex::new(r"^[A-Za-z]+$").unwrap();
-        if !name_regex.is_match(name) {
-            return Err("Lastname must contain only letters".to_string());
+    /// Validate a username; callers lowercase it first
+    pub fn validate_username(username: &str) -> Result<(), String> {
+        let username_regex = Regex::new(r"^[a-z0-9_]{3,30}$").unwrap();
+        if !username_regex.is_match(username) {
+            return Err("Username must be 3-30 letters, digits or underscores".to_string());
         }
-
         Ok(())
     }
 
-    /// Validate phone number
-    pub fn validate_phone(ph: &str) -> Result<(), String> {
+    /// Parse a phone number and return it in E.164 form (`+14155550123`).
+    /// Numbers without a `+` country code are read as local to
+    /// `PHONE_DEFAULT_REGION` (e.g. `US`) when that is set, and refused
+    /// otherwise. Numbers stored before E.164 are kept as they are; they are
+    /// only checked again when a profile edit sends a new `ph`.
+    pub fn normalize_phone(ph: &str) -> Result<String, String> {
+        let ph = ph.trim();
         if ph.is_empty() {
             return Err("Phone is required".to_string());
         }
 
-        if ph.len() < 10 || ph.len() > 15 {
-            return Err("Phone must be 10-15 digits".to_string());
+        let region = std::env::var("PHONE_DEFAULT_REGION")
+            .ok()
+            .and_then(|r| r.trim().to_uppercase().parse::<country::Id>().ok());
+        if region.is_none() && !ph.starts_with('+') {
+            return Err("Phone must include the country code, e.g. +14155550123".to_string());
         }
 
-        let phone_regex = Regex::new(r"^[0-9]+$").unwrap();
-        if !phone_regex.is_match(ph) {
-            return Err("Phone must contain only numbers".to_string());
+        let number = phonenumber::parse(region, ph).map_err(|_| "Phone number is invalid".to_string())?;
+        if !phonenumber::is_valid(&number) {
+            return Err("Phone number is invalid".to_string());
         }
 
-        Ok(())
+        Ok(number.format().mode(Mode::E164).to_string())
     }
 
     /// Validate image file type from filename
     pub fn validate_image_type(filename: &str) -> Result<(), String> {
         let allowed_extensions = ["jpg", "jpeg", "png", "webp"];
 
-        let extension = filename.split('.').last().unwrap_or("").to_lowercase();
+        let extension = filename.split('.').next_back().unwrap_or("").to_lowercase();
 
         if !allowed_extensions.contains(&extension.as_str()) {
             return Err("Image type must be jpeg, jpg,webp or png ".to_string());
@@ -129,25 +132,26 @@ impl Validator {
         Ok(())
     }
 
-    
-    pub fn validate_post_name(name: &str) -> Result<(), String> {
-        if name.trim().is_empty() {
-            return Err("Name is required".into());
-        }
-        if name.len() < 2 || name.len() > 100 {
-            return Err("Name length must be between 2 and 100 characters".into());
+    pub fn validate_post_visibility(visibility: &str) -> Result<(), String> {
+        if !POST_VISIBILITIES.contains(&visibility) {
+            return Err(format!(
+                "Visibility must be one of: {}",
+                POST_VISIBILITIES.join(", ")
+            ));
         }
         Ok(())
     }
 
-    pub fn validate_post_description(desc: &str) -> Result<(), String> {
-        if desc.trim().is_empty() {
-            return Err("Description is required".into());
-        }
-        if desc.len() < 3 || desc.len() > 500 {
-            return Err("Description length must be between 3 and 500 characters".into());
+    /// Parse an RFC 3339 `publish_at` and require it to lie in the future.
+    /// Returns the instant as naive UTC, the way timestamps are stored.
+    pub fn validate_publish_at(publish_at: &str) -> Result<NaiveDateTime, String> {
+        let parsed = DateTime::parse_from_rfc3339(publish_at.trim())
+            .map_err(|_| "publish_at must be an RFC 3339 timestamp".to_string())?;
+        let publish_at = parsed.with_timezone(&Utc);
+        if publish_at <= Utc::now() {
+            return Err("publish_at must be in the future".into());
         }
-        Ok(())
+        Ok(publish_at.naive_utc())
     }
 
     pub fn validate_post_images(filenames: &[String]) -> Result<(), String> {
@@ -160,8 +164,281 @@ impl Validator {
         Ok(())
     }
 
+    /// A message needs text, images or both; `image_count` is checked against the per-message cap.
+    pub fn validate_message(body: &str, image_count: usize) -> Result<(), String> {
+        if body.trim().is_empty() && image_count == 0 {
+            return Err("Message needs text or at least one image".into());
+        }
+        if body.chars().count() > 2000 {
+            return Err("Message must be at most 2000 characters".into());
+        }
+        if image_count > 4 {
+            return Err("A message can have at most 4 images".into());
+        }
+        Ok(())
+    }
+}
+
+// Field rules for the request DTOs. Each failure carries a stable `code`
+// for clients and a `message` ready to show.
+
+fn field_error(code: &'static str, message: String) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+/// Empty values are reported as `required`, anything else under `code`.
+fn check_with(value: &str, code: &'static str, rule: impl Fn(&str) -> Result<(), String>) -> Result<(), ValidationError> {
+    rule(value).map_err(|message| {
+        let code = if value.trim().is_empty() { "required" } else { code };
+        field_error(code, message)
+    })
+}
+
+pub fn check_email(value: &str) -> Result<(), ValidationError> {
+    check_with(value, "invalid_email", Validator::validate_email)
+}
+
+pub fn check_firstname(value: &str) -> Result<(), ValidationError> {
+    check_with(value, "invalid_name", |v| Validator::normalize_firstname(v).map(|_| ()))
+}
+
+pub fn check_lastname(value: &str) -> Result<(), ValidationError> {
+    check_with(value, "invalid_name", |v| Validator::normalize_lastname(v).map(|_| ()))
+}
+
+pub fn check_phone(value: &str) -> Result<(), ValidationError> {
+    check_with(value, "invalid_phone", |v| Validator::normalize_phone(v).map(|_| ()))
+}
+
+pub fn check_username(value: &str) -> Result<(), ValidationError> {
+    check_with(value, "invalid_username", Validator::validate_username)
+}
+
+pub fn check_visibility(value: &str) -> Result<(), ValidationError> {
+    check_with(value, "invalid_visibility", Validator::validate_post_visibility)
+}
+
+/// New posts start as drafts or go out right away; scheduling uses `publish_at`.
+pub fn check_new_post_status(value: &str) -> Result<(), ValidationError> {
+    if value == POST_STATUS_DRAFT || value == POST_STATUS_PUBLISHED {
+        Ok(())
+    } else {
+        Err(field_error("invalid_status", "Status must be draft or published".to_string()))
+    }
+}
+
+pub fn check_publish_at(value: &str) -> Result<(), ValidationError> {
+    check_with(value, "invalid_publish_at", |v| Validator::validate_publish_at(v).map(|_| ()))
+}
 
-    
+/// Add the password policy's findings to `errors` under `field`.
+pub fn add_password_issues(errors: &mut ValidationErrors, field: &'static str, issues: Vec<PasswordIssue>) {
+    for issue in issues {
+        errors.add(field, field_error(issue.code, issue.message));
+    }
 }
 
+/// Every failed rule, grouped by field: `{"email": [{"code", "message"}]}`.
+pub fn field_error_map(errors: &ValidationErrors) -> BTreeMap<String, Vec<serde_json::Value>> {
+    errors
+        .errors()
+        .iter()
+        .filter_map(|(field, kind)| match kind {
+            ValidationErrorsKind::Field(list) => Some((
+                field.to_string(),
+                list.iter()
+                    .map(|e| {
+                        serde_json::json!({
+                            "code": e.code,
+                            "message": e.message.as_deref().unwrap_or(&e.code)
+                        })
+                    })
+                    .collect(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 400 with the field error map; `message` repeats the first failure so
+/// older clients still have something to show.
+pub fn validation_response(errors: &ValidationErrors) -> HttpResponse {
+    let map = field_error_map(errors);
+    let first = map
+        .values()
+        .flatten()
+        .next()
+        .and_then(|e| e["message"].as_str())
+        .unwrap_or("Validation failed")
+        .to_string();
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "status": false,
+        "message": first,
+        "errors": map
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::{PostForm, ProfileEdit, RegisterForm};
+    use actix_web::body::MessageBody;
+    use validator::Validate;
+
+    fn register_form() -> RegisterForm {
+        RegisterForm {
+            email: "ada@example.com".to_string(),
+            firstname: "Ada".to_string(),
+            lastname: "Lovelace".to_string(),
+            ph: "+14155550123".to_string(),
+            password: "unused here".to_string(),
+            username: Some("ada_l".to_string()),
+        }
+    }
+
+    fn post_form() -> PostForm {
+        PostForm {
+            name: "Sunset".to_string(),
+            description: "Over the bay".to_string(),
+            visibility: "public".to_string(),
+            status: POST_STATUS_PUBLISHED.to_string(),
+            publish_at: None,
+        }
+    }
+
+    /// `field -> [code]` from a DTO's validation errors.
+    fn codes(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+        field_error_map(errors)
+            .into_iter()
+            .map(|(field, list)| {
+                let codes = list.iter().map(|e| e["code"].as_str().unwrap().to_string()).collect();
+                (field, codes)
+            })
+            .collect()
+    }
+
+    /// A field, how to break it, and the code it should then fail with.
+    type Case<F> = (&'static str, fn(&mut F), &'static str);
 
+    fn single(field: &str, code: &str) -> BTreeMap<String, Vec<String>> {
+        BTreeMap::from([(field.to_string(), vec![code.to_string()])])
+    }
+
+    #[test]
+    fn valid_forms_pass() {
+        assert!(register_form().validate().is_ok());
+        assert!(post_form().validate().is_ok());
+        let nothing = ProfileEdit { email: None, firstname: None, lastname: None, ph: None, password: None, username: None };
+        assert!(nothing.validate().is_ok());
+    }
+
+    #[test]
+    fn register_field_codes() {
+        let cases: [Case<RegisterForm>; 7] = [
+            ("email", |f| f.email = "not-an-email".into(), "invalid_email"),
+            ("email", |f| f.email = String::new(), "required"),
+            ("firstname", |f| f.firstname = "R2-D2".into(), "invalid_name"),
+            ("lastname", |f| f.lastname = "   ".into(), "required"),
+            ("ph", |f| f.ph = "+1 123".into(), "invalid_phone"),
+            ("username", |f| f.username = Some("No Spaces".into()), "invalid_username"),
+            ("username", |f| f.username = Some("ab".into()), "invalid_username"),
+        ];
+        for (field, edit, code) in cases {
+            let mut form = register_form();
+            edit(&mut form);
+            let errors = form.validate().unwrap_err();
+            assert_eq!(codes(&errors), single(field, code), "{} -> {}", field, code);
+        }
+    }
+
+    #[test]
+    fn profile_edit_checks_only_sent_fields() {
+        let edit = ProfileEdit {
+            email: None,
+            firstname: Some("9".to_string()),
+            lastname: None,
+            ph: Some("12345".to_string()),
+            password: None,
+            username: None,
+        };
+        let errors = edit.validate().unwrap_err();
+        let mut expected = single("firstname", "invalid_name");
+        expected.insert("ph".to_string(), vec!["invalid_phone".to_string()]);
+        assert_eq!(codes(&errors), expected);
+    }
+
+    #[test]
+    fn post_field_codes() {
+        let cases: [Case<PostForm>; 6] = [
+            ("name", |f| f.name = "x".into(), "invalid_length"),
+            ("description", |f| f.description = "y".repeat(501), "invalid_length"),
+            ("visibility", |f| f.visibility = "friends".into(), "invalid_visibility"),
+            ("status", |f| f.status = "scheduled".into(), "invalid_status"),
+            ("publish_at", |f| f.publish_at = Some("tomorrow".into()), "invalid_publish_at"),
+            ("publish_at", |f| f.publish_at = Some("2001-01-01T00:00:00Z".into()), "invalid_publish_at"),
+        ];
+        for (field, edit, code) in cases {
+            let mut form = post_form();
+            edit(&mut form);
+            let errors = form.validate().unwrap_err();
+            assert_eq!(codes(&errors), single(field, code), "{} -> {}", field, code);
+        }
+    }
+
+    #[test]
+    fn validation_response_shape() {
+        let mut form = register_form();
+        form.email = "nope".to_string();
+        form.firstname = String::new();
+        let errors = form.validate().unwrap_err();
+
+        let resp = validation_response(&errors);
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body = resp.into_body().try_into_bytes().ok().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json["status"], false);
+        assert_eq!(json["errors"]["email"][0]["code"], "invalid_email");
+        assert_eq!(json["errors"]["email"][0]["message"], "Email is invalid");
+        assert_eq!(json["errors"]["firstname"][0]["code"], "required");
+        // Fields come out in name order, so the first message is the email's
+        assert_eq!(json["message"], "Email is invalid");
+    }
+
+    #[test]
+    fn password_issues_keep_their_codes() {
+        let mut errors = ValidationErrors::new();
+        let issues = vec![PasswordIssue { code: "too_short", message: "Too short".to_string() }];
+        add_password_issues(&mut errors, "new_password", issues);
+        assert_eq!(codes(&errors), single("new_password", "too_short"));
+    }
+
+    #[test]
+    fn names_are_normalized() {
+        assert_eq!(Validator::normalize_firstname("  Mary   Jane ").unwrap(), "Mary Jane");
+        assert_eq!(Validator::normalize_lastname("O'Brien-Smith").unwrap(), "O'Brien-Smith");
+        assert_eq!(Validator::normalize_firstname("J. R.").unwrap(), "J. R.");
+        assert_eq!(Validator::normalize_lastname("Ñúñez").unwrap(), "Ñúñez");
+        // Decomposed input is stored composed
+        assert_eq!(Validator::normalize_lastname("Nu\u{301}n\u{303}ez").unwrap(), "Núñez");
+        assert_eq!(Validator::normalize_firstname("Σωκράτης").unwrap(), "Σωκράτης");
+    }
+
+    #[test]
+    fn bad_names_are_refused() {
+        for name in ["", "   ", "-Ann", "Ann-", "Ann--Marie", "Ann  -Marie", "R2D2", "Ann_Marie", "'Ann"] {
+            assert!(Validator::normalize_firstname(name).is_err(), "{:?}", name);
+        }
+        assert!(Validator::normalize_firstname(&"a".repeat(51)).is_err());
+        assert!(Validator::normalize_firstname(&"a".repeat(50)).is_ok());
+    }
+
+    #[test]
+    fn phones_are_stored_as_e164() {
+        assert_eq!(Validator::normalize_phone("+1 (415) 555-0123").unwrap(), "+14155550123");
+        assert_eq!(Validator::normalize_phone(" +44 20 7946 0958 ").unwrap(), "+442079460958");
+        assert!(Validator::normalize_phone("").is_err());
+        assert!(Validator::normalize_phone("+1 123").is_err());
+        assert!(Validator::normalize_phone("+not a number").is_err());
+    }
+}